@@ -1,58 +1,84 @@
-use std::fmt;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::BufRead;
 use std::io::BufReader;
 
-pub fn part_1(earliest_time: usize, times: &Vec<usize>) -> (usize, usize) {
-    let mut current_time = earliest_time;
-    loop {
-        for time in times {
-            if current_time % time == 0 {
-                return (current_time - earliest_time, *time);
-            }
-        }
-        current_time += 1;
-    }
-}
+use num::BigInt;
 
-pub fn part_2(times: &Vec<(usize, usize)>) -> usize {
-    let mut m = 0;
-    let mut period = 1;
+pub mod constraints;
+pub mod crt;
+pub mod schedule;
 
-    for (i, time) in times.iter() {
-        let mut k = 0;
-        while (period * k + m + i) % *time != 0 {
-            k += 1;
-        }
-
-        m += period * k;
-        period *= time;
-    }
+use constraints::{from_schedule, solve, Constraint};
+use schedule::Schedule;
 
-    m
+pub fn part_1(earliest_time: usize, times: &[usize]) -> (usize, usize) {
+    let (bus, departure) = Schedule::new(times).first_after(earliest_time).unwrap();
+    (departure - earliest_time, bus)
 }
 
-fn parse(s: &str) -> Vec<(usize, usize)> {
-    s.split(",")
-        .map(|s| s.parse())
-        .enumerate()
-        .filter_map(|(i, n)| n.ok().map(|n| (i, n)))
-        .collect()
+pub fn part_2(constraints: &[Constraint]) -> Option<BigInt> {
+    solve(constraints)
 }
 
-pub fn thirteen() -> Result<(), std::io::Error> {
+fn input() -> Result<Vec<String>, std::io::Error> {
     let file = File::open("13_input")?;
     let reader = BufReader::new(file);
-    let lines: Vec<String> = reader.lines().map(|s| s.unwrap()).collect();
+    Ok(reader.lines().map(|s| s.unwrap()).collect())
+}
+
+pub fn thirteen() -> Result<(), std::io::Error> {
+    let lines = input()?;
 
     let earliest_time: usize = lines[0].parse().unwrap();
-    let bus_times: Vec<usize> = lines[1].split(",").map(|s| s.parse()).flatten().collect();
+    let bus_times = Schedule::parse(&lines[1]);
 
-    let p1 = part_1(earliest_time, &bus_times);
+    let p1 = part_1(earliest_time, bus_times.buses());
     println!("Part 1: {}", p1.0 * p1.1);
 
-    let times: Vec<(usize, usize)> = parse(&lines[1]);
-    println!("Part 2: {}", part_2(&times));
+    match part_2(&from_schedule(&lines[1])) {
+        Some(t) => println!("Part 2: {}", t),
+        None => println!("Part 2: no timestamp works"),
+    }
+
+    Ok(())
+}
+
+// `13 departures <from> <to>`: every departure in the puzzle schedule
+// between the two times, inclusive.
+pub fn departures(from: &str, to: &str) -> Result<(), std::io::Error> {
+    let (from, to) = match (from.parse(), to.parse()) {
+        (Ok(from), Ok(to)) => (from, to),
+        _ => {
+            println!("usage: 13 departures <from> <to>");
+            return Ok(());
+        }
+    };
+
+    let schedule = Schedule::parse(&input()?[1]);
+    for (bus, departure) in schedule.departures_between(from, to) {
+        println!("{} {}", departure, bus);
+    }
+
+    Ok(())
+}
+
+// Solves a file of explicit `<bus> <offset>` constraints instead of the
+// puzzle schedule.
+pub fn solve_file(path: &str) -> Result<(), std::io::Error> {
+    let constraints = match constraints::parse(&fs::read_to_string(path)?) {
+        Ok(constraints) => constraints,
+        Err(errors) => {
+            for e in errors {
+                println!("{}", e);
+            }
+            return Ok(());
+        }
+    };
+
+    match solve(&constraints) {
+        Some(t) => println!("{}", t),
+        None => println!("no timestamp works"),
+    }
 
     Ok(())
 }
@@ -63,13 +89,17 @@ mod tests {
 
     #[test]
     fn test() {
-        assert_eq!((5, 59), part_1(939, &vec![7, 13, 59, 31, 19]));
-        assert_eq!(0, part_2(&parse("3")));
-        assert_eq!(9, part_2(&parse("3,5")));
-        assert_eq!(3417, part_2(&parse("17,x,13,19")));
-        assert_eq!(754018, part_2(&parse("67,7,59,61")));
-        assert_eq!(779210, part_2(&parse("67,x,7,59,61")));
-        assert_eq!(1261476, part_2(&parse("67,7,x,59,61")));
-        assert_eq!(1202161486, part_2(&parse("1789,37,47,1889")));
+        assert_eq!((5, 59), part_1(939, &[7, 13, 59, 31, 19]));
+        assert_eq!(Some(0.into()), part_2(&from_schedule("3")));
+        assert_eq!(Some(9.into()), part_2(&from_schedule("3,5")));
+        assert_eq!(Some(3417.into()), part_2(&from_schedule("17,x,13,19")));
+        assert_eq!(Some(754018.into()), part_2(&from_schedule("67,7,59,61")));
+        assert_eq!(Some(779210.into()), part_2(&from_schedule("67,x,7,59,61")));
+        assert_eq!(Some(1261476.into()), part_2(&from_schedule("67,7,x,59,61")));
+        assert_eq!(
+            Some(1202161486.into()),
+            part_2(&from_schedule("1789,37,47,1889"))
+        );
+        assert_eq!(None, part_2(&from_schedule("4,x,x,6")));
     }
 }
@@ -0,0 +1,158 @@
+use std::fmt;
+
+use num::BigInt;
+
+use crate::day_13::crt::crt;
+
+// Bus `bus` has to leave at t + offset. The offset may be negative or larger
+// than the bus id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Constraint {
+    pub bus: u64,
+    pub offset: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ErrorKind {
+    Fields(usize),
+    InvalidBus(String),
+    InvalidOffset(String),
+}
+
+// Lines are numbered from 1.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ErrorKind::Fields(n) => {
+                write!(f, "expected a bus id and an offset, found {} fields", n)
+            }
+            ErrorKind::InvalidBus(s) => write!(f, "{:?} is not a bus id", s),
+            ErrorKind::InvalidOffset(s) => write!(f, "{:?} is not an offset", s),
+        }
+    }
+}
+
+// The puzzle's schedule, where the bus at index i leaves at t + i. Like x,
+// a bus id of 0 is not a bus and is skipped.
+pub fn from_schedule(s: &str) -> Vec<Constraint> {
+    s.split(',')
+        .enumerate()
+        .filter_map(|(i, bus)| match bus.parse() {
+            Ok(bus) if bus > 0 => Some(Constraint {
+                bus,
+                offset: i as i64,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn constraint(s: &str) -> Result<Constraint, ErrorKind> {
+    let fields = s.split_whitespace().collect::<Vec<_>>();
+    let (bus, offset) = match fields.as_slice() {
+        [bus, offset] => (bus, offset),
+        _ => return Err(ErrorKind::Fields(fields.len())),
+    };
+
+    let bus = match bus.parse() {
+        Ok(bus) if bus > 0 => bus,
+        _ => return Err(ErrorKind::InvalidBus(bus.to_string())),
+    };
+    let offset = offset
+        .parse()
+        .map_err(|_| ErrorKind::InvalidOffset(offset.to_string()))?;
+    Ok(Constraint { bus, offset })
+}
+
+// One `<bus> <offset>` pair per line, with blank lines and lines starting
+// with # ignored.
+pub fn parse(s: &str) -> Result<Vec<Constraint>, Vec<ParseError>> {
+    let mut constraints = Vec::new();
+    let mut errors = Vec::new();
+
+    for (i, line) in s.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match constraint(line) {
+            Ok(c) => constraints.push(c),
+            Err(kind) => errors.push(ParseError { line: i + 1, kind }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(constraints)
+    } else {
+        Err(errors)
+    }
+}
+
+// The earliest t >= 0 meeting every constraint, if there is one.
+pub fn solve(constraints: &[Constraint]) -> Option<BigInt> {
+    let congruences = constraints
+        .iter()
+        .map(|c| (-BigInt::from(c.offset), BigInt::from(c.bus)))
+        .collect::<Vec<_>>();
+    crt(&congruences).map(|(t, _)| t)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_13::constraints::*;
+
+    #[test]
+    fn test() {
+        assert_eq!(
+            vec![
+                Constraint { bus: 17, offset: 0 },
+                Constraint { bus: 13, offset: 2 },
+                Constraint { bus: 19, offset: 3 }
+            ],
+            from_schedule("17,x,13,19")
+        );
+        assert_eq!(
+            vec![Constraint { bus: 3, offset: 2 }],
+            from_schedule("0,x,3")
+        );
+        assert_eq!(Some(1.into()), solve(&from_schedule("0,x,3")));
+
+        let constraints = parse(
+            "\
+# the puzzle example, written out
+17 0
+13 2
+
+19 +3
+",
+        )
+        .unwrap();
+        assert_eq!(from_schedule("17,x,13,19"), constraints);
+        assert_eq!(Some(3417.into()), solve(&constraints));
+
+        // bus 5 leaves 7 minutes before t and bus 3 leaves 20 minutes after
+        let constraints = parse("5 -7\n3 20").unwrap();
+        assert_eq!(Some(7.into()), solve(&constraints));
+        assert_eq!(None, solve(&parse("4 0\n6 1").unwrap()));
+
+        assert_eq!(
+            vec![
+                "line 1: expected a bus id and an offset, found 1 fields",
+                "line 2: \"0\" is not a bus id",
+                "line 4: \"x\" is not an offset"
+            ],
+            parse("17\n0 1\n3 1\n3 x")
+                .unwrap_err()
+                .iter()
+                .map(|e| e.to_string())
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use num::{BigInt, Integer, One, Signed, Zero};
+
+// Returns (g, x, y) with a * x + b * y = g = gcd(a, b).
+pub fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut old_r, mut r) = (a.clone(), b.clone());
+    let (mut old_x, mut x) = (BigInt::one(), BigInt::zero());
+    let (mut old_y, mut y) = (BigInt::zero(), BigInt::one());
+
+    while !r.is_zero() {
+        let q = &old_r / &r;
+        let next_r = &old_r - &q * &r;
+        old_r = std::mem::replace(&mut r, next_r);
+        let next_x = &old_x - &q * &x;
+        old_x = std::mem::replace(&mut x, next_x);
+        let next_y = &old_y - &q * &y;
+        old_y = std::mem::replace(&mut y, next_y);
+    }
+
+    if old_r.is_negative() {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+// Merges x = r1 (mod m1) and x = r2 (mod m2) into a single congruence modulo
+// lcm(m1, m2), or None if no x satisfies both. The moduli need not be
+// coprime, only positive.
+pub fn combine(a: (&BigInt, &BigInt), b: (&BigInt, &BigInt)) -> Option<(BigInt, BigInt)> {
+    let ((r1, m1), (r2, m2)) = (a, b);
+    assert!(
+        m1.is_positive() && m2.is_positive(),
+        "moduli must be positive"
+    );
+
+    let (g, p, _) = extended_gcd(m1, m2);
+    let diff = r2 - r1;
+    if !diff.is_multiple_of(&g) {
+        return None;
+    }
+
+    let lcm = m1 / &g * m2;
+    let k = (&diff / &g * p).mod_floor(&(m2 / &g));
+    Some(((r1 + m1 * k).mod_floor(&lcm), lcm))
+}
+
+// Solves x = r (mod m) for every (r, m), returning the smallest non-negative
+// x together with the modulus all solutions repeat with.
+pub fn crt(congruences: &[(BigInt, BigInt)]) -> Option<(BigInt, BigInt)> {
+    congruences
+        .iter()
+        .try_fold((BigInt::zero(), BigInt::one()), |(r, m), (r2, m2)| {
+            combine((&r, &m), (r2, m2))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_13::crt::*;
+    use num::ToPrimitive;
+
+    fn solve(congruences: &[(i64, i64)]) -> Option<(i64, i64)> {
+        let congruences = congruences
+            .iter()
+            .map(|(r, m)| (BigInt::from(*r), BigInt::from(*m)))
+            .collect::<Vec<_>>();
+        crt(&congruences).map(|(x, m)| (x.to_i64().unwrap(), m.to_i64().unwrap()))
+    }
+
+    #[test]
+    fn test() {
+        let (g, x, y) = extended_gcd(&BigInt::from(240), &BigInt::from(46));
+        assert_eq!(BigInt::from(2), g);
+        assert_eq!(g, x * 240 + y * 46);
+
+        assert_eq!(Some((0, 1)), solve(&[]));
+        assert_eq!(Some((23, 105)), solve(&[(2, 3), (3, 5), (2, 7)]));
+        // 6 and 10 share a factor of 2, so the period is their lcm
+        assert_eq!(Some((13, 30)), solve(&[(1, 6), (3, 10)]));
+        assert_eq!(None, solve(&[(1, 6), (2, 10)]));
+        assert_eq!(Some((4, 5)), solve(&[(-1, 5), (9, 5)]));
+        assert_eq!(None, solve(&[(0, 4), (1, 4)]));
+
+        let big = "170141183460469231731687303715884105727"
+            .parse::<BigInt>()
+            .unwrap();
+        let (x, m) = crt(&[
+            (BigInt::from(1), big.clone()),
+            (BigInt::from(2), BigInt::from(u64::MAX)),
+        ])
+        .unwrap();
+        assert_eq!(big * u64::MAX, m);
+        assert_eq!(
+            BigInt::from(1),
+            &x % "170141183460469231731687303715884105727"
+                .parse::<BigInt>()
+                .unwrap()
+        );
+        assert_eq!(BigInt::from(2), x % u64::MAX);
+    }
+}
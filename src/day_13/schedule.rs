@@ -0,0 +1,91 @@
+// Departure times are inclusive: a bus leaving exactly at t counts as the
+// next departure after t.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schedule {
+    buses: Vec<usize>,
+}
+
+impl Schedule {
+    pub fn new(buses: &[usize]) -> Schedule {
+        assert!(buses.iter().all(|&bus| bus > 0), "bus ids must be positive");
+        Schedule {
+            buses: buses.to_vec(),
+        }
+    }
+
+    // The second line of the notes, with x for buses out of service. A bus
+    // id of 0 is skipped the same way.
+    pub fn parse(s: &str) -> Schedule {
+        Schedule::new(
+            &s.split(',')
+                .flat_map(|s| s.parse())
+                .filter(|&bus| bus > 0)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    pub fn buses(&self) -> &[usize] {
+        &self.buses
+    }
+
+    pub fn next_departure(bus: usize, t: usize) -> usize {
+        t.div_ceil(bus) * bus
+    }
+
+    // (bus, departure) for every bus, in the order of the schedule.
+    pub fn departures_after(&self, t: usize) -> Vec<(usize, usize)> {
+        self.buses
+            .iter()
+            .map(|&bus| (bus, Schedule::next_departure(bus, t)))
+            .collect()
+    }
+
+    // Ties go to the bus listed first.
+    pub fn first_after(&self, t: usize) -> Option<(usize, usize)> {
+        self.departures_after(t)
+            .into_iter()
+            .min_by_key(|&(_, departure)| departure)
+    }
+
+    // (bus, departure) for every departure in [a, b], ordered by time.
+    pub fn departures_between(&self, a: usize, b: usize) -> Vec<(usize, usize)> {
+        let mut res = Vec::new();
+        for &bus in &self.buses {
+            let mut departure = Schedule::next_departure(bus, a);
+            while departure <= b {
+                res.push((bus, departure));
+                departure += bus;
+            }
+        }
+        res.sort_by_key(|&(_, departure)| departure);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_13::schedule::*;
+
+    #[test]
+    fn test() {
+        let schedule = Schedule::parse("7,13,x,x,59,x,31,19");
+        assert_eq!(&[7, 13, 59, 31, 19], schedule.buses());
+        assert_eq!(&[3], Schedule::parse("0,x,3").buses());
+
+        assert_eq!(945, Schedule::next_departure(7, 939));
+        assert_eq!(938, Schedule::next_departure(7, 938));
+        assert_eq!(
+            vec![(7, 945), (13, 949), (59, 944), (31, 961), (19, 950)],
+            schedule.departures_after(939)
+        );
+        assert_eq!(Some((59, 944)), schedule.first_after(939));
+        assert_eq!(Some((7, 0)), schedule.first_after(0));
+        assert_eq!(None, Schedule::new(&[]).first_after(939));
+
+        assert_eq!(
+            vec![(59, 944), (7, 945), (13, 949), (19, 950), (7, 952)],
+            schedule.departures_between(940, 952)
+        );
+        assert!(schedule.departures_between(950, 949).is_empty());
+    }
+}
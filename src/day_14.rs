@@ -1,156 +1,117 @@
-use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
 
-#[derive(Debug)]
-enum Instruction {
-    Mask(Vec<Option<bool>>),
-    Mem(u64, u64),
+pub mod bench;
+pub mod computer;
+pub mod dump;
+pub mod parser;
+pub mod ternary;
+
+use computer::{word, Decoder, DockingComputer};
+use parser::parse;
+use ternary::Pattern;
+
+const WIDTH: u32 = 36;
+
+// `and` keeps the bits of a value under an X, `or` sets the 1s and
+// `floating` marks the Xs themselves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mask {
+    and: u64,
+    or: u64,
+    floating: u64,
+    width: u32,
 }
 
-impl Instruction {
-    fn new(s: &str) -> Instruction {
-        match s.chars().skip(1).next().unwrap() {
-            'a' => Instruction::Mask(
-                s.split(" = ")
-                    .skip(1)
-                    .next()
-                    .unwrap()
-                    .chars()
-                    .map(|c| match c {
-                        '1' => Some(true),
-                        '0' => Some(false),
-                        'X' => None,
-                        _ => unreachable!(),
-                    })
-                    .collect(),
-            ),
-            'e' => {
-                let mut s1 = s.split("] = ");
-                let n1 = s1
-                    .next()
-                    .unwrap()
-                    .split("[")
-                    .skip(1)
-                    .next()
-                    .unwrap()
-                    .parse()
-                    .unwrap();
-                let n2 = s1.next().unwrap().parse().unwrap();
-
-                Instruction::Mem(n1, n2)
-            }
-            _ => unreachable!(),
-        }
+impl Default for Mask {
+    fn default() -> Mask {
+        Mask::all(WIDTH)
     }
 }
 
-fn parse<S: AsRef<str>>(input: &[S]) -> Vec<Instruction> {
-    input
-        .into_iter()
-        .map(|s| Instruction::new(s.as_ref()))
-        .collect()
-}
-
-fn as_bits(n: u64) -> Vec<bool> {
-    format!("{:#038b}", n)
-        .chars()
-        .skip(2)
-        .map(|c| match c {
-            '0' => false,
-            '1' => true,
-            _ => unreachable!(),
-        })
-        .collect()
-}
-
-fn from_bits(n: Vec<bool>) -> u64 {
-    let mut res = 0;
-    for bit in n {
-        res <<= 1;
-        res |= if bit { 1 } else { 0 };
+impl Mask {
+    // The mask a computer starts with, which leaves everything unchanged.
+    fn all(width: u32) -> Mask {
+        Mask {
+            and: word(width),
+            or: 0,
+            floating: word(width),
+            width,
+        }
     }
-    res
-}
 
-fn apply_mask(value: &[bool], mask: &[Option<bool>]) -> Vec<bool> {
-    value
-        .iter()
-        .zip(mask.iter())
-        .map(|(v, mask)| mask.unwrap_or(*v))
-        .collect()
-}
-
-fn part_1(instructions: &[Instruction]) -> u64 {
-    let mut mask: Vec<Option<bool>> = (0..36).into_iter().map(|_| None).collect();
-    let mut memory: HashMap<u64, u64> = HashMap::new();
-
-    for instr in instructions {
-        match instr {
-            Instruction::Mask(m) => mask = m.to_vec(),
-            Instruction::Mem(location, value) => {
-                let write_val = from_bits(apply_mask(&as_bits(*value), &mask));
-                memory.insert(*location, write_val);
+    fn new(s: &str) -> Mask {
+        let mut mask = Mask {
+            and: 0,
+            or: 0,
+            floating: 0,
+            width: s.chars().count() as u32,
+        };
+        for c in s.chars() {
+            mask.and <<= 1;
+            mask.or <<= 1;
+            mask.floating <<= 1;
+            match c {
+                '1' => mask.or |= 1,
+                '0' => {}
+                'X' => {
+                    mask.and |= 1;
+                    mask.floating |= 1;
+                }
+                _ => unreachable!(),
             }
         }
+        mask
     }
 
-    memory.values().sum()
-}
-
-fn gen_masks(start: usize, address: &[bool], mask: &mut [Option<bool>]) -> Vec<Vec<bool>> {
-    let mut res = Vec::new();
-    for i in start..mask.len() {
-        match mask[i] {
-            None => {
-                mask[i] = Some(true);
-                res.append(&mut gen_masks(i + 1, address, mask));
-                mask[i] = Some(false);
-                res.append(&mut gen_masks(i + 1, address, mask));
-                mask[i] = None;
-                break;
-            }
-            Some(false) => {
-                mask[i] = Some(address[i]);
-            }
-            Some(true) => {}
-        }
+    fn apply(&self, value: u64) -> u64 {
+        (value & self.and) | self.or
     }
-    if mask.iter().all(|s| s.is_some()) {
-        vec![mask.iter().map(|s| s.unwrap()).collect()]
-    } else {
-        res
+
+    // All the addresses the mask decodes `address` to, as a single pattern.
+    fn pattern(&self, address: u64) -> Pattern {
+        Pattern::new(address | self.or, self.floating)
     }
 }
 
-fn part_2(instructions: &[Instruction]) -> u64 {
-    let mut mask: Vec<Option<bool>> = (0..36).into_iter().map(|_| None).collect();
-    let mut memory: HashMap<u64, u64> = HashMap::new();
-
-    for instr in instructions {
-        match instr {
-            Instruction::Mask(m) => mask = m.to_vec(),
-            Instruction::Mem(location, value) => {
-                let mut mask_buf = mask.clone();
-                for addr_mask in gen_masks(0, &as_bits(*location), &mut mask_buf) {
-                    let address = from_bits(addr_mask);
-                    memory.insert(address, *value);
-                }
-            }
-        }
-    }
+#[derive(Debug, PartialEq)]
+pub enum Instruction {
+    Mask(Mask),
+    Mem(u64, u64),
+}
+
+fn run(instructions: &[Instruction], decoder: Decoder) -> u128 {
+    let mut computer = DockingComputer::new(WIDTH, decoder);
+    computer.run(instructions).unwrap();
+    computer.sum()
+}
+
+fn part_1(instructions: &[Instruction]) -> u128 {
+    run(instructions, Decoder::Value)
+}
 
-    memory.values().sum()
+fn part_2(instructions: &[Instruction]) -> u128 {
+    run(instructions, Decoder::Address)
 }
 
 pub fn fourteen() -> Result<(), std::io::Error> {
     let file = File::open("14_input")?;
     let reader = BufReader::new(file);
-    let lines: Vec<Instruction> = parse(&reader.lines().map(|s| s.unwrap()).collect::<Vec<_>>());
+    let lines = reader.lines().map(|s| s.unwrap()).collect::<Vec<_>>();
 
-    println!("Part 1: {}", part_1(&lines));
-    println!("Part 2: {}", part_2(&lines));
+    let instrs = match parse(&lines, WIDTH) {
+        Ok(instrs) => instrs,
+        Err(errors) => {
+            for e in errors {
+                println!("{}", e);
+            }
+            return Ok(());
+        }
+    };
+
+    println!("Part 1: {}", part_1(&instrs));
+    println!("Part 2: {}", part_2(&instrs));
 
     Ok(())
 }
@@ -161,25 +122,61 @@ mod tests {
 
     #[test]
     fn test() {
-        let instrs = parse(&vec![
-            "mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X",
-            "mem[8] = 11",
-            "mem[7] = 101",
-            "mem[8] = 0",
-        ]);
+        let instrs = parse(
+            &[
+                "mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X",
+                "mem[8] = 11",
+                "mem[7] = 101",
+                "mem[8] = 0",
+            ],
+            WIDTH,
+        )
+        .unwrap();
 
         assert_eq!(165, part_1(&instrs));
     }
 
     #[test]
     fn test_2() {
-        let instrs = parse(&vec![
-            "mask = 000000000000000000000000000000X1001X",
-            "mem[42] = 100",
-            "mask = 00000000000000000000000000000000X0XX",
-            "mem[26] = 1",
-        ]);
+        let instrs = parse(
+            &[
+                "mask = 000000000000000000000000000000X1001X",
+                "mem[42] = 100",
+                "mask = 00000000000000000000000000000000X0XX",
+                "mem[26] = 1",
+            ],
+            WIDTH,
+        )
+        .unwrap();
 
         assert_eq!(208, part_2(&instrs));
     }
+
+    #[test]
+    fn floating() {
+        let instrs = parse(
+            &[
+                "mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX00",
+                "mem[0] = 1",
+                "mask = 1XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX",
+                "mem[0] = 2",
+            ],
+            WIDTH,
+        )
+        .unwrap();
+
+        assert_eq!((1 << 33) + (1 << 36), part_2(&instrs));
+    }
+
+    #[test]
+    fn masks() {
+        let pattern = Mask::new("000000000000000000000000000000X1001X").pattern(42);
+        assert_eq!(4, pattern.len());
+        assert!([59, 58, 27, 26].iter().all(|&a| pattern.contains(a)));
+        assert_eq!(
+            73,
+            Mask::new("XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X").apply(11)
+        );
+        assert_eq!(Pattern::new(2, 0b101), Mask::new("X0X").pattern(3));
+    }
 }
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+
+use crate::day_14::parser::parse;
+use crate::day_14::{part_1, part_2, Instruction, Mask, WIDTH};
+use crate::utils::time;
+
+// The Vec<bool> masks from before the integer ones.
+fn as_bits(n: u64) -> Vec<bool> {
+    format!("{:#038b}", n)
+        .chars()
+        .skip(2)
+        .map(|c| match c {
+            '0' => false,
+            '1' => true,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+fn from_bits(n: Vec<bool>) -> u64 {
+    let mut res = 0;
+    for bit in n {
+        res <<= 1;
+        res |= if bit { 1 } else { 0 };
+    }
+    res
+}
+
+fn mask_bits(mask: &Mask) -> Vec<Option<bool>> {
+    (0..36)
+        .rev()
+        .map(|i| {
+            if mask.floating >> i & 1 == 1 {
+                None
+            } else {
+                Some(mask.or >> i & 1 == 1)
+            }
+        })
+        .collect()
+}
+
+fn apply_mask(value: &[bool], mask: &[Option<bool>]) -> Vec<bool> {
+    value
+        .iter()
+        .zip(mask.iter())
+        .map(|(v, mask)| mask.unwrap_or(*v))
+        .collect()
+}
+
+fn bits_part_1(instructions: &[Instruction]) -> u64 {
+    let mut mask: Vec<Option<bool>> = (0..36).map(|_| None).collect();
+    let mut memory: HashMap<u64, u64> = HashMap::new();
+
+    for instr in instructions {
+        match instr {
+            Instruction::Mask(m) => mask = mask_bits(m),
+            Instruction::Mem(location, value) => {
+                let write_val = from_bits(apply_mask(&as_bits(*value), &mask));
+                memory.insert(*location, write_val);
+            }
+        }
+    }
+
+    memory.values().sum()
+}
+
+fn gen_masks(start: usize, address: &[bool], mask: &mut [Option<bool>]) -> Vec<Vec<bool>> {
+    let mut res = Vec::new();
+    for i in start..mask.len() {
+        match mask[i] {
+            None => {
+                mask[i] = Some(true);
+                res.append(&mut gen_masks(i + 1, address, mask));
+                mask[i] = Some(false);
+                res.append(&mut gen_masks(i + 1, address, mask));
+                mask[i] = None;
+                break;
+            }
+            Some(false) => {
+                mask[i] = Some(address[i]);
+            }
+            Some(true) => {}
+        }
+    }
+    if mask.iter().all(|s| s.is_some()) {
+        vec![mask.iter().map(|s| s.unwrap()).collect()]
+    } else {
+        res
+    }
+}
+
+fn bits_part_2(instructions: &[Instruction]) -> u64 {
+    let mut mask: Vec<Option<bool>> = (0..36).map(|_| None).collect();
+    let mut memory: HashMap<u64, u64> = HashMap::new();
+
+    for instr in instructions {
+        match instr {
+            Instruction::Mask(m) => mask = mask_bits(m),
+            Instruction::Mem(location, value) => {
+                let mut mask_buf = mask.clone();
+                for addr_mask in gen_masks(0, &as_bits(*location), &mut mask_buf) {
+                    let address = from_bits(addr_mask);
+                    memory.insert(address, *value);
+                }
+            }
+        }
+    }
+
+    memory.values().sum()
+}
+
+// The integer decoder from before the ternary memory, which writes every
+// address the mask decodes to by counting through the subsets of its Xs.
+fn integers_part_2(instructions: &[Instruction]) -> u64 {
+    let mut mask = Mask::default();
+    let mut memory: HashMap<u64, u64> = HashMap::new();
+
+    for instr in instructions {
+        match instr {
+            Instruction::Mask(m) => mask = *m,
+            Instruction::Mem(location, value) => {
+                let base = (location | mask.or) & !mask.floating;
+                let mut subset = mask.floating;
+                loop {
+                    memory.insert(base | subset, *value);
+                    if subset == 0 {
+                        break;
+                    }
+                    subset = (subset - 1) & mask.floating;
+                }
+            }
+        }
+    }
+
+    memory.values().sum()
+}
+
+pub fn bench() -> Result<(), std::io::Error> {
+    let file = File::open("14_input")?;
+    let reader = BufReader::new(file);
+    let lines = reader.lines().map(|s| s.unwrap()).collect::<Vec<_>>();
+    let instrs = parse(&lines, WIDTH).unwrap();
+
+    let (old, old_time) = time(100, || bits_part_1(&instrs));
+    let (new, new_time) = time(100, || part_1(&instrs));
+    assert_eq!(old as u128, new);
+    println!("Part 1: bits {:?}, integers {:?}", old_time, new_time);
+
+    let (old, old_time) = time(10, || bits_part_2(&instrs));
+    let (ints, ints_time) = time(10, || integers_part_2(&instrs));
+    let (new, new_time) = time(10, || part_2(&instrs));
+    assert_eq!(old, ints);
+    assert_eq!(old as u128, new);
+    println!(
+        "Part 2: bits {:?}, integers {:?}, patterns {:?}",
+        old_time, ints_time, new_time
+    );
+
+    Ok(())
+}
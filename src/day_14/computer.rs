@@ -0,0 +1,187 @@
+use std::fmt;
+
+use crate::day_14::ternary::{Memory, Pattern};
+use crate::day_14::{Instruction, Mask};
+
+// What the mask is applied to on a write: version 1 chips mask the value,
+// version 2 chips decode the address into every address the mask matches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Decoder {
+    Value,
+    Address,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ComputerError {
+    MaskWidth { width: u32, found: u32 },
+    ValueTooWide { value: u64, width: u32 },
+    AddressTooWide { address: u64, width: u32 },
+}
+
+impl fmt::Display for ComputerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ComputerError::MaskWidth { width, found } => {
+                write!(f, "mask has {} bits but the word is {} bits", found, width)
+            }
+            ComputerError::ValueTooWide { value, width } => {
+                write!(f, "value {} does not fit in {} bits", value, width)
+            }
+            ComputerError::AddressTooWide { address, width } => {
+                write!(f, "address {} does not fit in {} bits", address, width)
+            }
+        }
+    }
+}
+
+// All the bits of a word of the given width.
+pub fn word(width: u32) -> u64 {
+    u64::MAX >> (64 - width)
+}
+
+#[derive(Clone, Debug)]
+pub struct DockingComputer {
+    width: u32,
+    decoder: Decoder,
+    mask: Mask,
+    memory: Memory,
+    executed: usize,
+}
+
+impl DockingComputer {
+    pub fn new(width: u32, decoder: Decoder) -> DockingComputer {
+        assert!(
+            (1..=64).contains(&width),
+            "word width must be between 1 and 64 bits"
+        );
+        DockingComputer {
+            width,
+            decoder,
+            mask: Mask::all(width),
+            memory: Memory::new(),
+            executed: 0,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    // Instructions are numbered in the order they are executed, which is what
+    // the memory records as the last writer of each address.
+    pub fn execute(&mut self, instr: &Instruction) -> Result<(), ComputerError> {
+        let instruction = self.executed;
+        self.executed += 1;
+
+        match *instr {
+            Instruction::Mask(mask) if mask.width != self.width => Err(ComputerError::MaskWidth {
+                width: self.width,
+                found: mask.width,
+            }),
+            Instruction::Mask(mask) => {
+                self.mask = mask;
+                Ok(())
+            }
+            Instruction::Mem(address, _) if address & !word(self.width) != 0 => {
+                Err(ComputerError::AddressTooWide {
+                    address,
+                    width: self.width,
+                })
+            }
+            Instruction::Mem(_, value) if value & !word(self.width) != 0 => {
+                Err(ComputerError::ValueTooWide {
+                    value,
+                    width: self.width,
+                })
+            }
+            Instruction::Mem(address, value) => {
+                match self.decoder {
+                    Decoder::Value => self.memory.write(
+                        Pattern::new(address, 0),
+                        self.mask.apply(value),
+                        instruction,
+                    ),
+                    Decoder::Address => {
+                        self.memory
+                            .write(self.mask.pattern(address), value, instruction)
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Stops at the first instruction the computer rejects, returning its
+    // index alongside the error.
+    pub fn run(&mut self, instrs: &[Instruction]) -> Result<(), (usize, ComputerError)> {
+        for (i, instr) in instrs.iter().enumerate() {
+            self.execute(instr).map_err(|e| (i, e))?;
+        }
+        Ok(())
+    }
+
+    pub fn sum(&self) -> u128 {
+        self.memory.sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_14::computer::*;
+    use crate::day_14::parser::parse;
+
+    #[test]
+    fn test() {
+        let instrs = parse(&["mask = 1X0X", "mem[3] = 6", "mem[12] = 15"], 4).unwrap();
+
+        let mut v1 = DockingComputer::new(4, Decoder::Value);
+        assert_eq!(Ok(()), v1.run(&instrs));
+        assert_eq!(Some(0b1100), v1.memory().get(3));
+        assert_eq!(0b1100 + 0b1101, v1.sum());
+
+        let mut v2 = DockingComputer::new(4, Decoder::Address);
+        assert_eq!(Ok(()), v2.run(&instrs));
+        // 3 decodes to 1X1X and 12 to 1X0X
+        assert_eq!(Some(6), v2.memory().get(0b1010));
+        assert_eq!(Some(15), v2.memory().get(0b1001));
+        assert_eq!(4 * 6 + 4 * 15, v2.sum());
+
+        let mut narrow = DockingComputer::new(3, Decoder::Value);
+        assert_eq!(
+            Err((0, ComputerError::MaskWidth { width: 3, found: 4 })),
+            narrow.run(&instrs)
+        );
+        assert_eq!(
+            Err((
+                1,
+                ComputerError::ValueTooWide {
+                    value: 15,
+                    width: 3
+                }
+            )),
+            narrow.run(&parse(&["mem[1] = 7", "mem[2] = 15"], 4).unwrap())
+        );
+        assert_eq!(
+            Err((
+                0,
+                ComputerError::AddressTooWide {
+                    address: 8,
+                    width: 3
+                }
+            )),
+            narrow.run(&parse(&["mem[8] = 1"], 4).unwrap())
+        );
+
+        let mut wide = DockingComputer::new(64, Decoder::Address);
+        let mask = format!("mask = {}", "X".repeat(64));
+        assert_eq!(
+            Ok(()),
+            wide.run(&parse(&[&mask, "mem[0] = 1"], 64).unwrap())
+        );
+        assert_eq!(1 << 64, wide.sum());
+    }
+}
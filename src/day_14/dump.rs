@@ -0,0 +1,265 @@
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+
+use crate::day_14::computer::{Decoder, DockingComputer};
+use crate::day_14::parser::parse;
+use crate::day_14::ternary::{Memory, Pattern};
+use crate::day_14::WIDTH;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Csv,
+}
+
+// Addresses matched by `pattern` where the two memories hold different
+// values, None meaning never written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Difference {
+    pub pattern: Pattern,
+    pub left: Option<u64>,
+    pub right: Option<u64>,
+}
+
+fn address(pattern: &Pattern, width: u32) -> String {
+    (0..width)
+        .rev()
+        .map(|i| {
+            if pattern.floating >> i & 1 == 1 {
+                'X'
+            } else if pattern.value >> i & 1 == 1 {
+                '1'
+            } else {
+                '0'
+            }
+        })
+        .collect()
+}
+
+fn table(header: &[&str], rows: Vec<Vec<String>>, format: Format) -> String {
+    let mut out = String::new();
+    let rows = std::iter::once(header.iter().map(|s| s.to_string()).collect()).chain(rows);
+
+    match format {
+        Format::Csv => {
+            for row in rows {
+                out.push_str(&row.join(","));
+                out.push('\n');
+            }
+        }
+        Format::Text => {
+            let rows = rows.collect::<Vec<Vec<String>>>();
+            let widths = (0..header.len())
+                .map(|i| rows.iter().map(|row| row[i].len()).max().unwrap())
+                .collect::<Vec<_>>();
+            for row in rows {
+                let cells = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                    .collect::<Vec<_>>();
+                out.push_str(cells.join("  ").trim_end());
+                out.push('\n');
+            }
+        }
+    }
+
+    out
+}
+
+// Instructions are shown numbered from 1 so they line up with the lines of
+// the program.
+pub fn dump(computer: &DockingComputer, format: Format) -> String {
+    let rows = computer
+        .memory()
+        .entries()
+        .into_iter()
+        .map(|(pattern, write)| {
+            vec![
+                address(&pattern, computer.width()),
+                write.value.to_string(),
+                (write.instruction + 1).to_string(),
+            ]
+        })
+        .collect();
+    table(&["address", "value", "instruction"], rows, format)
+}
+
+fn subtract_all(pattern: Pattern, others: &[(Pattern, u64)]) -> Vec<Pattern> {
+    let mut rest = vec![pattern];
+    for (other, _) in others {
+        rest = rest.iter().flat_map(|p| p.subtract(other)).collect();
+    }
+    rest
+}
+
+pub fn diff(left: &Memory, right: &Memory) -> Vec<Difference> {
+    let values = |memory: &Memory| {
+        memory
+            .entries()
+            .into_iter()
+            .map(|(pattern, write)| (pattern, write.value))
+            .collect::<Vec<_>>()
+    };
+    let (left, right) = (values(left), values(right));
+    let mut res = Vec::new();
+
+    for (l, lvalue) in &left {
+        for (r, rvalue) in &right {
+            match l.intersect(r) {
+                Some(pattern) if lvalue != rvalue => res.push(Difference {
+                    pattern,
+                    left: Some(*lvalue),
+                    right: Some(*rvalue),
+                }),
+                _ => {}
+            }
+        }
+        res.extend(
+            subtract_all(*l, &right)
+                .into_iter()
+                .map(|pattern| Difference {
+                    pattern,
+                    left: Some(*lvalue),
+                    right: None,
+                }),
+        );
+    }
+    for (r, rvalue) in &right {
+        res.extend(
+            subtract_all(*r, &left)
+                .into_iter()
+                .map(|pattern| Difference {
+                    pattern,
+                    left: None,
+                    right: Some(*rvalue),
+                }),
+        );
+    }
+
+    res.sort_by_key(|d| (d.pattern.value, d.pattern.floating));
+    res
+}
+
+pub fn render_diff(diffs: &[Difference], width: u32, labels: [&str; 2], format: Format) -> String {
+    let missing = match format {
+        Format::Text => "-",
+        Format::Csv => "",
+    };
+    let value = |v: Option<u64>| v.map(|v| v.to_string()).unwrap_or(missing.to_string());
+    let rows = diffs
+        .iter()
+        .map(|d| vec![address(&d.pattern, width), value(d.left), value(d.right)])
+        .collect();
+    table(&["address", labels[0], labels[1]], rows, format)
+}
+
+const USAGE: &str = "usage: 14 dump [v1|v2] [csv] or 14 diff [csv]";
+
+// `14 dump` and `14 diff` on the puzzle input.
+pub fn run(command: &str, options: &[&str]) -> Result<(), std::io::Error> {
+    let file = File::open("14_input")?;
+    let reader = BufReader::new(file);
+    let lines = reader.lines().map(|s| s.unwrap()).collect::<Vec<_>>();
+    let instrs = match parse(&lines, WIDTH) {
+        Ok(instrs) => instrs,
+        Err(errors) => {
+            for e in errors {
+                println!("{}", e);
+            }
+            return Ok(());
+        }
+    };
+
+    let mut format = Format::Text;
+    let mut decoder = Decoder::Address;
+    for option in options {
+        match *option {
+            "csv" => format = Format::Csv,
+            "v1" if command == "dump" => decoder = Decoder::Value,
+            "v2" if command == "dump" => decoder = Decoder::Address,
+            _ => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+        }
+    }
+
+    let computer = |decoder| {
+        let mut computer = DockingComputer::new(WIDTH, decoder);
+        computer.run(&instrs).unwrap();
+        computer
+    };
+    match command {
+        "dump" => print!("{}", dump(&computer(decoder), format)),
+        _ => {
+            let (v1, v2) = (computer(Decoder::Value), computer(Decoder::Address));
+            let diffs = diff(v1.memory(), v2.memory());
+            print!("{}", render_diff(&diffs, WIDTH, ["v1", "v2"], format));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_14::dump::*;
+
+    #[test]
+    fn test() {
+        let instrs = parse(
+            &["mask = X10X", "mem[3] = 6", "mem[12] = 9", "mem[0] = 2"],
+            4,
+        )
+        .unwrap();
+        let run = |decoder| {
+            let mut computer = DockingComputer::new(4, decoder);
+            computer.run(&instrs).unwrap();
+            computer
+        };
+        let (v1, v2) = (run(Decoder::Value), run(Decoder::Address));
+
+        assert_eq!(
+            "\
+address  value  instruction
+0000     4      4
+0011     4      2
+1100     13     3
+",
+            dump(&v1, Format::Text)
+        );
+        assert_eq!(
+            "address,value,instruction\nX10X,2,4\nX11X,6,2\n",
+            dump(&v2, Format::Csv)
+        );
+
+        assert_eq!(
+            "\
+address  v1  v2
+0000     4   -
+0011     4   -
+0100     -   2
+X101     -   2
+X11X     -   6
+1100     13  2
+",
+            render_diff(
+                &diff(v1.memory(), v2.memory()),
+                4,
+                ["v1", "v2"],
+                Format::Text
+            )
+        );
+        assert_eq!(
+            "address,left,right\n0000,4,\n0011,4,\n1100,13,\n",
+            render_diff(
+                &diff(v1.memory(), &Memory::new()),
+                4,
+                ["left", "right"],
+                Format::Csv
+            )
+        );
+    }
+}
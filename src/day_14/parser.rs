@@ -0,0 +1,201 @@
+use std::fmt;
+
+use crate::day_14::computer::word;
+use crate::day_14::{Instruction, Mask};
+
+#[derive(Debug, PartialEq)]
+pub enum ErrorKind {
+    MissingEquals,
+    UnknownInstruction(String),
+    MaskLength { expected: u32, found: usize },
+    InvalidMaskDigit { ch: char, column: usize },
+    MissingBracket(char),
+    InvalidNumber(String),
+    OutOfRange { value: String, width: u32 },
+}
+
+// Lines are numbered from 1.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub kind: ErrorKind,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            ErrorKind::MissingEquals => write!(f, "expected '='"),
+            ErrorKind::UnknownInstruction(name) => write!(f, "unknown instruction {:?}", name),
+            ErrorKind::MaskLength { expected, found } => {
+                write!(f, "mask has {} bits, expected {}", found, expected)
+            }
+            ErrorKind::InvalidMaskDigit { ch, column } => {
+                write!(f, "invalid mask digit {:?} at column {}", ch, column)
+            }
+            ErrorKind::MissingBracket(ch) => write!(f, "missing '{}'", ch),
+            ErrorKind::InvalidNumber(s) => write!(f, "{:?} is not a number", s),
+            ErrorKind::OutOfRange { value, width } => {
+                write!(f, "{} does not fit in {} bits", value, width)
+            }
+        }
+    }
+}
+
+fn number(s: &str, width: u32) -> Result<u64, ErrorKind> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) {
+        return Err(ErrorKind::InvalidNumber(s.to_string()));
+    }
+    match s.parse::<u64>() {
+        Ok(n) if n & !word(width) == 0 => Ok(n),
+        _ => Err(ErrorKind::OutOfRange {
+            value: s.to_string(),
+            width,
+        }),
+    }
+}
+
+// `start` is the offset of the mask in the line, for reporting columns.
+fn mask(s: &str, start: usize, width: u32) -> Result<Mask, ErrorKind> {
+    if let Some((i, ch)) = s
+        .chars()
+        .enumerate()
+        .find(|(_, c)| !matches!(c, '0' | '1' | 'X'))
+    {
+        return Err(ErrorKind::InvalidMaskDigit {
+            ch,
+            column: start + i + 1,
+        });
+    }
+    let found = s.chars().count();
+    if found != width as usize {
+        return Err(ErrorKind::MaskLength {
+            expected: width,
+            found,
+        });
+    }
+    Ok(Mask::new(s))
+}
+
+// mask = <width of 0, 1 or X>
+// mem[<address>] = <value>
+fn instruction(s: &str, width: u32) -> Result<Instruction, ErrorKind> {
+    let eq = s.find('=').ok_or(ErrorKind::MissingEquals)?;
+    let (lhs, rhs) = (s[..eq].trim(), &s[eq + 1..]);
+    let start = s.len() - rhs.trim_start().len();
+    let rhs = rhs.trim();
+
+    if lhs == "mask" {
+        return Ok(Instruction::Mask(mask(rhs, start, width)?));
+    }
+
+    match lhs.strip_prefix("mem") {
+        Some(address) => {
+            let address = address
+                .strip_prefix('[')
+                .ok_or(ErrorKind::MissingBracket('['))?
+                .strip_suffix(']')
+                .ok_or(ErrorKind::MissingBracket(']'))?;
+            Ok(Instruction::Mem(
+                number(address, width)?,
+                number(rhs, width)?,
+            ))
+        }
+        None => Err(ErrorKind::UnknownInstruction(lhs.to_string())),
+    }
+}
+
+// Checks every line and reports all the bad ones rather than stopping at the
+// first.
+pub fn parse<S: AsRef<str>>(input: &[S], width: u32) -> Result<Vec<Instruction>, Vec<ParseError>> {
+    let mut instrs = Vec::new();
+    let mut errors = Vec::new();
+
+    for (i, s) in input.iter().enumerate() {
+        match instruction(s.as_ref(), width) {
+            Ok(instr) => instrs.push(instr),
+            Err(kind) => errors.push(ParseError { line: i + 1, kind }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(instrs)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_14::parser::*;
+
+    fn error(s: &str) -> ErrorKind {
+        parse(&[s], 4).unwrap_err().remove(0).kind
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!(
+            Ok(vec![
+                Instruction::Mask(Mask::new("1X0X")),
+                Instruction::Mem(15, 0)
+            ]),
+            parse(&["mask = 1X0X", "mem[15]=0"], 4)
+        );
+
+        assert_eq!(ErrorKind::MissingEquals, error("mask 1X0X"));
+        assert_eq!(
+            ErrorKind::UnknownInstruction("mas".to_string()),
+            error("mas = 1X0X")
+        );
+        assert_eq!(
+            ErrorKind::MaskLength {
+                expected: 4,
+                found: 5
+            },
+            error("mask = 1X0X0")
+        );
+        assert_eq!(
+            ErrorKind::InvalidMaskDigit {
+                ch: 'x',
+                column: 10
+            },
+            error("mask = 1Xx0")
+        );
+        assert_eq!(ErrorKind::MissingBracket('['), error("mem 3] = 1"));
+        assert_eq!(ErrorKind::MissingBracket(']'), error("mem[3 = 1"));
+        assert_eq!(
+            ErrorKind::InvalidNumber("-1".to_string()),
+            error("mem[3] = -1")
+        );
+        assert_eq!(ErrorKind::InvalidNumber("".to_string()), error("mem[] = 1"));
+        assert_eq!(
+            ErrorKind::OutOfRange {
+                value: "16".to_string(),
+                width: 4
+            },
+            error("mem[16] = 1")
+        );
+        assert_eq!(
+            ErrorKind::OutOfRange {
+                value: "99999999999999999999".to_string(),
+                width: 4
+            },
+            error("mem[1] = 99999999999999999999")
+        );
+
+        let errors = parse(
+            &["mask = 1X0X", "mem[1] = 2", "mem[1 = 2", "", "mask = 10"],
+            4,
+        )
+        .unwrap_err();
+        assert_eq!(
+            vec![
+                "line 3: missing ']'",
+                "line 4: expected '='",
+                "line 5: mask has 2 bits, expected 4"
+            ],
+            errors.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+
+// A set of addresses written as a ternary pattern: bits set in `floating`
+// are X and match both 0 and 1, the others must equal the bit in `value`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Pattern {
+    pub value: u64,
+    pub floating: u64,
+}
+
+impl Pattern {
+    pub fn new(value: u64, floating: u64) -> Pattern {
+        Pattern {
+            value: value & !floating,
+            floating,
+        }
+    }
+
+    pub fn len(&self) -> u128 {
+        1 << self.floating.count_ones()
+    }
+
+    pub fn contains(&self, address: u64) -> bool {
+        (address ^ self.value) & !self.floating == 0
+    }
+
+    pub fn intersect(&self, other: &Pattern) -> Option<Pattern> {
+        let fixed = !self.floating & !other.floating;
+        if (self.value ^ other.value) & fixed != 0 {
+            return None;
+        }
+        Some(Pattern::new(
+            self.value | other.value,
+            self.floating & other.floating,
+        ))
+    }
+
+    // The addresses matched by self but not by other, as disjoint patterns.
+    // Each floating bit of self that other fixes splits off the half which
+    // disagrees with other, and the rest carries on with that bit pinned.
+    pub fn subtract(&self, other: &Pattern) -> Vec<Pattern> {
+        let common = match self.intersect(other) {
+            Some(common) => common,
+            None => return vec![*self],
+        };
+
+        let mut res = Vec::new();
+        let mut rest = *self;
+        let mut split = self.floating & !common.floating;
+        while split != 0 {
+            let bit = split & split.wrapping_neg();
+            split &= !bit;
+            rest.floating &= !bit;
+            res.push(Pattern::new(
+                rest.value | (!common.value & bit),
+                rest.floating,
+            ));
+            rest.value |= common.value & bit;
+        }
+        res
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Write {
+    pub value: u64,
+    pub instruction: usize,
+}
+
+// Memory kept as non-overlapping patterns, each holding the last write to
+// all of its addresses. Single addresses, which is all the value decoder
+// ever writes, live in a map instead of being scanned on every write.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Memory {
+    exact: HashMap<u64, Write>,
+    regions: HashMap<Pattern, Write>,
+}
+
+impl Memory {
+    pub fn new() -> Memory {
+        Memory::default()
+    }
+
+    pub fn write(&mut self, pattern: Pattern, value: u64, instruction: usize) {
+        if pattern.floating != 0 {
+            self.exact.retain(|address, _| !pattern.contains(*address));
+        }
+
+        let mut split = Vec::new();
+        self.regions.retain(|region, old| {
+            if region.intersect(&pattern).is_none() {
+                return true;
+            }
+            split.extend(region.subtract(&pattern).into_iter().map(|p| (p, *old)));
+            false
+        });
+        for (piece, old) in split {
+            self.insert(piece, old);
+        }
+
+        let write = Write { value, instruction };
+        if pattern.floating == 0 {
+            self.exact.insert(pattern.value, write);
+        } else {
+            self.regions.insert(pattern, write);
+        }
+    }
+
+    // Adds a region left over from a write, joined with any sibling which
+    // differs from it in a single fixed bit and holds the same write, as
+    // otherwise overlapping writes keep cutting memory into smaller pieces.
+    fn insert(&mut self, mut pattern: Pattern, write: Write) {
+        let mut fixed = !pattern.floating;
+        while fixed != 0 {
+            let bit = fixed & fixed.wrapping_neg();
+            fixed &= !bit;
+            let sibling = Pattern::new(pattern.value ^ bit, pattern.floating);
+            if self.regions.get(&sibling) == Some(&write) {
+                self.regions.remove(&sibling);
+                pattern = Pattern::new(pattern.value, pattern.floating | bit);
+                fixed = !pattern.floating;
+            }
+        }
+        self.regions.insert(pattern, write);
+    }
+
+    #[cfg(test)]
+    pub fn last_write(&self, address: u64) -> Option<Write> {
+        self.exact.get(&address).copied().or_else(|| {
+            self.regions
+                .iter()
+                .find(|(pattern, _)| pattern.contains(address))
+                .map(|(_, write)| *write)
+        })
+    }
+
+    #[cfg(test)]
+    pub fn get(&self, address: u64) -> Option<u64> {
+        self.last_write(address).map(|write| write.value)
+    }
+
+    // Everything written, ordered by address.
+    pub fn entries(&self) -> Vec<(Pattern, Write)> {
+        let mut entries = self
+            .exact
+            .iter()
+            .map(|(address, write)| (Pattern::new(*address, 0), *write))
+            .chain(
+                self.regions
+                    .iter()
+                    .map(|(pattern, write)| (*pattern, *write)),
+            )
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(pattern, _)| (pattern.value, pattern.floating));
+        entries
+    }
+
+    pub fn sum(&self) -> u128 {
+        let exact = self
+            .exact
+            .values()
+            .map(|write| write.value as u128)
+            .sum::<u128>();
+        exact
+            + self
+                .regions
+                .iter()
+                .map(|(pattern, write)| pattern.len() * write.value as u128)
+                .sum::<u128>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_14::ternary::*;
+
+    #[test]
+    fn test() {
+        // 1X0X and XX01
+        let a = Pattern::new(0b1000, 0b0101);
+        let b = Pattern::new(0b0001, 0b1100);
+        assert_eq!(Some(Pattern::new(0b1001, 0b0100)), a.intersect(&b));
+        assert_eq!(None, a.intersect(&Pattern::new(0b0000, 0b0101)));
+
+        let rest = a.subtract(&b);
+        assert_eq!(vec![Pattern::new(0b1000, 0b0100)], rest);
+        assert_eq!(a.len() - 2, rest.iter().map(|p| p.len()).sum::<u128>());
+        for address in 0..16 {
+            let in_rest = rest.iter().filter(|p| p.contains(address)).count();
+            let expected = a.contains(address) && !b.contains(address);
+            assert_eq!(expected as usize, in_rest, "{:04b}", address);
+        }
+
+        let mut memory = Memory::new();
+        memory.write(a, 3, 0);
+        memory.write(b, 5, 1);
+        assert_eq!(Some(5), memory.get(0b1101));
+        assert_eq!(Some(3), memory.get(0b1100));
+        assert_eq!(None, memory.get(0b0100));
+        assert_eq!(2 * 3 + 4 * 5, memory.sum());
+
+        memory.write(Pattern::new(0b1100, 0), 7, 2);
+        memory.write(Pattern::new(0b0100, 0), 2, 3);
+        assert_eq!(Some(7), memory.get(0b1100));
+        assert_eq!(
+            Some(Write {
+                value: 3,
+                instruction: 0
+            }),
+            memory.last_write(0b1000)
+        );
+        assert_eq!(3 + 7 + 2 + 4 * 5, memory.sum());
+        assert_eq!(
+            vec![
+                (Pattern::new(0b0001, 0b1100), 1),
+                (Pattern::new(0b0100, 0), 3),
+                (Pattern::new(0b1000, 0), 0),
+                (Pattern::new(0b1100, 0), 2),
+            ],
+            memory
+                .entries()
+                .into_iter()
+                .map(|(pattern, write)| (pattern, write.instruction))
+                .collect::<Vec<_>>()
+        );
+
+        memory.write(Pattern::new(0b0000, 0b1111), 1, 4);
+        assert_eq!(1, memory.entries().len());
+        assert_eq!(16, memory.sum());
+
+        // writing 00X leaves X1X and 10X of XXX, then writing 01X leaves
+        // 11X, which joins 10X back into 1XX
+        let mut memory = Memory::new();
+        memory.write(Pattern::new(0, 0b111), 1, 0);
+        memory.write(Pattern::new(0b000, 0b001), 2, 1);
+        memory.write(Pattern::new(0b010, 0b001), 3, 2);
+        assert_eq!(
+            vec![
+                (Pattern::new(0b000, 0b001), 1),
+                (Pattern::new(0b010, 0b001), 2),
+                (Pattern::new(0b100, 0b011), 0),
+            ],
+            memory
+                .entries()
+                .into_iter()
+                .map(|(pattern, write)| (pattern, write.instruction))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn overlapping() {
+        // 30 floating bits each, with the 6 fixed ones among the low 12, so
+        // the last write to an address only depends on its low 12 bits
+        let mut seed: u64 = 1;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        let mut writes = Vec::new();
+        let mut memory = Memory::new();
+        for instruction in 0..400 {
+            let mut floating: u64 = 0xF_FFFF_F000;
+            while floating.count_ones() < 30 {
+                floating |= 1 << (next() % 12);
+            }
+            let pattern = Pattern::new(next() & 0xF_FFFF_FFFF, floating);
+            let value = next() % 1000;
+            memory.write(pattern, value, instruction);
+            writes.push((pattern, value));
+        }
+
+        let expected = (0..1 << 12)
+            .map(|low| {
+                let last = writes.iter().rev().find(|(p, _)| p.contains(low));
+                last.map(|(_, value)| *value as u128).unwrap_or(0) << 24
+            })
+            .sum::<u128>();
+        assert_eq!(expected, memory.sum());
+
+        for (pattern, write) in &memory.regions {
+            for bit in 0..36 {
+                let sibling = Pattern::new(pattern.value ^ 1 << bit, pattern.floating);
+                if pattern.floating >> bit & 1 == 0 {
+                    assert_ne!(Some(write), memory.regions.get(&sibling));
+                }
+            }
+        }
+    }
+}
@@ -3,54 +3,151 @@ use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
 
-fn simulator(lines: &[usize], turn_limit: usize) -> usize {
-    let mut memory: HashMap<usize, (usize, Option<usize>)> = HashMap::new();
-    let mut prev = 0;
-    let mut turn = 0;
-
-    for line in lines {
-        turn += 1;
-        memory.insert(*line, (turn, None));
-        prev = *line;
+pub mod bench;
+
+// Remembers the turn each number was last spoken on. Turns start at 1.
+pub trait LastSeen {
+    // Records `n` as spoken on `turn`, returning the turn it was spoken on
+    // before that.
+    fn insert(&mut self, n: usize, turn: usize) -> Option<usize>;
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Hashed(HashMap<usize, usize>);
+
+impl LastSeen for Hashed {
+    fn insert(&mut self, n: usize, turn: usize) -> Option<usize> {
+        self.0.insert(n, turn)
+    }
+}
+
+// Every number spoken after the starting ones is smaller than the turn
+// limit, so they all fit in the table. Only starting numbers at or above the
+// limit end up in the map. 0 marks a number which has not been spoken yet.
+#[derive(Clone, Debug)]
+pub struct Flat {
+    table: Vec<u32>,
+    overflow: Hashed,
+}
+
+impl Flat {
+    pub fn new(turn_limit: usize) -> Flat {
+        assert!(
+            turn_limit <= u32::MAX as usize,
+            "turn limit does not fit in the table"
+        );
+        Flat {
+            table: vec![0; turn_limit],
+            overflow: Hashed::default(),
+        }
+    }
+}
+
+impl LastSeen for Flat {
+    fn insert(&mut self, n: usize, turn: usize) -> Option<usize> {
+        match self.table.get_mut(n) {
+            Some(seen) => {
+                let prev = std::mem::replace(seen, turn as u32);
+                if prev == 0 {
+                    None
+                } else {
+                    Some(prev as usize)
+                }
+            }
+            None => self.overflow.insert(n, turn),
+        }
+    }
+}
+
+impl<M: LastSeen> LastSeen for &mut M {
+    fn insert(&mut self, n: usize, turn: usize) -> Option<usize> {
+        (**self).insert(n, turn)
+    }
+}
+
+// Yields (turn, number) for every number spoken, starting at turn 1 with the
+// starting numbers. The game never ends, so bound it with take or nth.
+#[derive(Clone, Debug)]
+pub struct MemoryGame<'a, M = Hashed> {
+    lines: &'a [usize],
+    memory: M,
+    turn: usize,
+    prev: usize,
+}
+
+impl<'a> MemoryGame<'a> {
+    #[cfg(test)]
+    pub fn new(lines: &'a [usize]) -> MemoryGame<'a> {
+        MemoryGame::with_memory(lines, Hashed::default())
     }
+}
 
-    loop {
-        if turn == turn_limit {
-            return prev;
+impl<'a, M: LastSeen> MemoryGame<'a, M> {
+    pub fn with_memory(lines: &'a [usize], memory: M) -> MemoryGame<'a, M> {
+        MemoryGame {
+            lines,
+            memory,
+            turn: 0,
+            prev: 0,
         }
+    }
+}
 
-        turn += 1;
+impl<'a, M: LastSeen> Iterator for MemoryGame<'a, M> {
+    type Item = (usize, usize);
 
-        let next = if let (_, Some(diff_to_prev_pos)) = memory[&prev] {
-            diff_to_prev_pos
+    fn next(&mut self) -> Option<(usize, usize)> {
+        if self.lines.is_empty() {
+            return None;
+        }
+
+        let seen = if self.turn > 0 {
+            self.memory.insert(self.prev, self.turn)
         } else {
-            0
+            None
+        };
+        let next = match self.lines.get(self.turn) {
+            Some(n) => *n,
+            None => seen.map(|seen| self.turn - seen).unwrap_or(0),
         };
 
-        match memory.get(&next).copied() {
-            None => {
-                memory.insert(next, (turn, None));
-            }
-            Some((prev_pos, _)) => {
-                memory.insert(next, (turn, Some(turn - prev_pos)));
-            }
-        }
+        self.turn += 1;
+        self.prev = next;
+        Some((self.turn, next))
+    }
+}
 
-        prev = next;
+pub fn play<M: LastSeen>(lines: &[usize], turn_limit: usize, memory: &mut M) -> usize {
+    assert!(!lines.is_empty(), "the game needs starting numbers");
+    MemoryGame::with_memory(lines, memory)
+        .nth(turn_limit - 1)
+        .map(|(_, n)| n)
+        .unwrap()
+}
+
+fn simulator(lines: &[usize], turn_limit: usize) -> usize {
+    if turn_limit <= u32::MAX as usize {
+        play(lines, turn_limit, &mut Flat::new(turn_limit))
+    } else {
+        play(lines, turn_limit, &mut Hashed::default())
     }
 }
 
-pub fn fifteen() -> Result<(), std::io::Error> {
+pub fn input() -> Result<Vec<usize>, std::io::Error> {
     let file = File::open("15_input")?;
     let reader = BufReader::new(file);
-    let lines: Vec<usize> = reader
+    Ok(reader
         .lines()
         .next()
         .unwrap()
         .unwrap()
-        .split(",")
+        .split(',')
         .map(|s| s.parse().unwrap())
-        .collect();
+        .collect())
+}
+
+pub fn fifteen() -> Result<(), std::io::Error> {
+    let lines = input()?;
 
     println!("Part 1: {}", simulator(&lines, 2020));
     println!("Part 2: {}", simulator(&lines, 30000000));
@@ -67,4 +164,71 @@ mod tests {
         assert_eq!(436, simulator(&[0, 3, 6], 2020));
         assert_eq!(175594, simulator(&[0, 3, 6], 30000000));
     }
+
+    #[test]
+    fn backends() {
+        for (lines, expected) in [
+            (vec![1, 3, 2], 1),
+            (vec![2, 1, 3], 10),
+            (vec![3, 1, 2], 1836),
+        ] {
+            assert_eq!(expected, play(&lines, 2020, &mut Hashed::default()));
+            assert_eq!(expected, play(&lines, 2020, &mut Flat::new(2020)));
+        }
+        assert_eq!(3, simulator(&[0, 3, 6], 2));
+        assert_eq!(3, play(&[0, 3, 6], 5, &mut Flat::new(5)));
+    }
+
+    // The original simulator recorded every starting number as never spoken
+    // before, so a repeat among them was missed and this gave 16.
+    #[test]
+    fn repeated_starting_numbers() {
+        let lines = [5000, 3, 5000];
+        assert_eq!(
+            vec![5000, 3, 5000, 2, 0, 0, 1, 0, 2, 5],
+            MemoryGame::new(&lines)
+                .map(|(_, n)| n)
+                .take(10)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(4, play(&lines, 2020, &mut Hashed::default()));
+        assert_eq!(4, play(&lines, 2020, &mut Flat::new(2020)));
+        assert_eq!(4, simulator(&lines, 2020));
+    }
+
+    #[test]
+    fn game() {
+        let lines = [0, 3, 6];
+        assert_eq!(
+            vec![0, 3, 6, 0, 3, 3, 1, 0, 4, 0],
+            MemoryGame::new(&lines)
+                .map(|(_, n)| n)
+                .take(10)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![(9, 4), (10, 0)],
+            MemoryGame::new(&lines).skip(8).take(2).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some((2020, 436)),
+            MemoryGame::with_memory(&lines, Flat::new(2020)).nth(2019)
+        );
+
+        // a flat table smaller than the game just spills into its map
+        assert_eq!(
+            MemoryGame::new(&lines).nth(2019),
+            MemoryGame::with_memory(&lines, Flat::new(10)).nth(2019)
+        );
+
+        assert_eq!(Some((9, 4)), MemoryGame::new(&lines).find(|&(_, n)| n == 4));
+        assert_eq!(
+            4,
+            MemoryGame::new(&lines)
+                .take(10)
+                .filter(|&(_, n)| n == 0)
+                .count()
+        );
+        assert_eq!(None, MemoryGame::new(&[]).next());
+    }
 }
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+use crate::day_15::{input, play, Flat, Hashed};
+use crate::utils::time;
+
+// The simulator from before the last-seen tables.
+fn original_simulator(lines: &[usize], turn_limit: usize) -> usize {
+    let mut memory: HashMap<usize, (usize, Option<usize>)> = HashMap::new();
+    let mut prev = 0;
+    let mut turn = 0;
+
+    for line in lines {
+        turn += 1;
+        memory.insert(*line, (turn, None));
+        prev = *line;
+    }
+
+    loop {
+        if turn == turn_limit {
+            return prev;
+        }
+
+        turn += 1;
+
+        let next = if let (_, Some(diff_to_prev_pos)) = memory[&prev] {
+            diff_to_prev_pos
+        } else {
+            0
+        };
+
+        match memory.get(&next).copied() {
+            None => {
+                memory.insert(next, (turn, None));
+            }
+            Some((prev_pos, _)) => {
+                memory.insert(next, (turn, Some(turn - prev_pos)));
+            }
+        }
+
+        prev = next;
+    }
+}
+
+pub fn bench() -> Result<(), std::io::Error> {
+    let lines = input()?;
+    let limit = 30000000;
+
+    let (original, original_time) = time(1, || original_simulator(&lines, limit));
+    let (hashed, hashed_time) = time(1, || play(&lines, limit, &mut Hashed::default()));
+    let (flat, flat_time) = time(1, || play(&lines, limit, &mut Flat::new(limit)));
+    assert_eq!(original, hashed);
+    assert_eq!(original, flat);
+    println!(
+        "Part 2: original {:?}, hashed {:?}, flat {:?}",
+        original_time, hashed_time, flat_time
+    );
+
+    Ok(())
+}
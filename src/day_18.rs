@@ -1,118 +1,136 @@
-use std::collections::VecDeque;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
 
-#[derive(Debug, PartialEq)]
-enum Token {
-    Number(i64),
-    Plus,
-    Times,
-    OpenParen,
-    CloseParen,
-}
+use num::BigInt;
+
+pub mod bench;
+mod bytecode;
+mod display;
+mod eval;
+mod lexer;
+mod number;
+mod parser;
+pub mod repl;
+mod simplify;
+
+use eval::{eval, Env, EvalError};
+use lexer::{tokenize, LexError, Span};
+use parser::{parse_all, OperatorTable, ParseError};
 
 #[derive(Debug, PartialEq)]
-enum Node {
-    Number(i64),
-    Plus(Box<Node>, Box<Node>),
-    Times(Box<Node>, Box<Node>),
+pub enum Error {
+    Lex(LexError),
+    Parse(ParseError),
+    Eval(EvalError),
 }
 
-impl Token {
-    fn new(s: char) -> Token {
-        match s {
-            '+' => Token::Plus,
-            '*' => Token::Times,
-            '(' => Token::OpenParen,
-            ')' => Token::CloseParen,
-            _ => Token::Number(s.to_digit(10).unwrap() as i64),
-        }
+impl From<LexError> for Error {
+    fn from(e: LexError) -> Error {
+        Error::Lex(e)
     }
 }
 
-fn tokenize(s: &str) -> Vec<Token> {
-    s.chars()
-        .filter(|c| *c != ' ')
-        .map(|c| Token::new(c))
-        .collect()
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Error {
+        Error::Parse(e)
+    }
 }
 
-fn eval(node: Node) -> i64 {
-    match node {
-        Node::Number(n) => n,
-        Node::Plus(a, b) => eval(*a) + eval(*b),
-        Node::Times(a, b) => eval(*a) * eval(*b),
+impl From<EvalError> for Error {
+    fn from(e: EvalError) -> Error {
+        Error::Eval(e)
     }
 }
 
-fn parse_one_1(tokens: &mut VecDeque<Token>) -> Node {
-    match tokens.pop_front().unwrap() {
-        Token::Number(n) => Node::Number(n),
-        Token::OpenParen => {
-            let inner = parse(tokens);
-            assert_eq!(Token::CloseParen, tokens.pop_front().unwrap());
-            inner
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Lex(e) => write!(f, "{}", e),
+            Error::Parse(e) => write!(f, "{}", e),
+            Error::Eval(e) => write!(f, "{}", e),
         }
-        other => panic!("Invalid token: {:?}", other),
     }
 }
 
-fn parse(tokens: &mut VecDeque<Token>) -> Node {
-    let mut left = parse_one_1(tokens);
-    loop {
-        match tokens.front() {
-            None | Some(Token::CloseParen) => break,
-            Some(Token::Plus) => {
-                assert_eq!(Token::Plus, tokens.pop_front().unwrap());
-                left = Node::Plus(Box::new(left), Box::new(parse_one_1(tokens)));
-            }
-            Some(Token::Times) => {
-                assert_eq!(Token::Times, tokens.pop_front().unwrap());
-                left = Node::Times(Box::new(left), Box::new(parse_one_1(tokens)));
-            }
-            other => panic!("Invalid token: {:?}", other),
+impl Error {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::Lex(e) => Some(e.span()),
+            Error::Parse(e) => Some(e.span()),
+            Error::Eval(_) => None,
         }
     }
-    left
 }
 
-fn calculate(s: &str) -> i64 {
-    let mut tokens = tokenize(s).into_iter().collect();
-    eval(parse(&mut tokens))
+// Shows the message with the offending part of the line underlined.
+pub fn caret(line: &str, e: &Error) -> String {
+    match e.span() {
+        Some(span) => format!(
+            "error: {}\n  {}\n  {}{}",
+            e,
+            line,
+            " ".repeat(span.start),
+            "^".repeat((span.end - span.start).max(1))
+        ),
+        None => format!("error: {}", e),
+    }
 }
 
-fn parse_2(tokens: &mut VecDeque<Token>, prec: bool) -> Node {
-    let mut left = match tokens.pop_front().unwrap() {
-        Token::Number(n) => Node::Number(n),
-        Token::OpenParen => {
-            let inner = parse_2(tokens, false);
-            assert_eq!(Token::CloseParen, tokens.pop_front().unwrap());
-            inner
-        }
-        other => panic!("Invalid token: {:?}", other),
-    };
-    loop {
-        match tokens.front() {
-            None | Some(Token::CloseParen) => break,
-            Some(Token::Plus) => {
-                assert_eq!(Token::Plus, tokens.pop_front().unwrap());
-                left = Node::Plus(Box::new(left), Box::new(parse_2(tokens, true)));
-            }
-            Some(Token::Times) if prec => break,
-            Some(Token::Times) => {
-                assert_eq!(Token::Times, tokens.pop_front().unwrap());
-                left = Node::Times(Box::new(left), Box::new(parse_2(tokens, false)));
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    Number(i64),
+    BigNumber(BigInt),
+    Variable(String),
+    Neg(Box<Node>),
+    Binary(BinOp, Box<Node>, Box<Node>),
+    Call(String, Vec<Node>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Statement {
+    Expr(Node),
+    Assign(String, Node),
+    Function(String, Vec<String>, Node),
+}
+
+// Evaluates every line it can, collecting the errors of the others by line
+// number rather than giving up at the first bad one.
+pub fn evaluate_lines(
+    lines: &[String],
+    table: &OperatorTable,
+) -> (Vec<i64>, BTreeMap<usize, Vec<Error>>) {
+    let mut values = Vec::new();
+    let mut errors = BTreeMap::new();
+    let env = Env::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let node = match tokenize(line) {
+            Ok(tokens) => parse_all(&tokens, table)
+                .map_err(|errors| errors.into_iter().map(Error::from).collect()),
+            Err(e) => Err(vec![Error::from(e)]),
+        };
+        match node.and_then(|node| eval(&node, &env).map_err(|e| vec![Error::from(e)])) {
+            Ok(value) => values.push(value),
+            Err(e) => {
+                errors.insert(i + 1, e);
             }
-            other => panic!("Invalid token: {:?}", other),
         }
     }
-    left
-}
 
-fn calculate_2(s: &str) -> i64 {
-    let mut tokens = tokenize(s).into_iter().collect();
-    eval(parse_2(&mut tokens, false))
+    (values, errors)
 }
 
 pub fn eighteen() -> Result<(), std::io::Error> {
@@ -120,46 +138,181 @@ pub fn eighteen() -> Result<(), std::io::Error> {
     let reader = BufReader::new(file);
     let lines = &reader.lines().map(|s| s.unwrap()).collect::<Vec<_>>();
 
-    println!(
-        "Part 1: {}",
-        lines.iter().map(|s| calculate(&s)).sum::<i64>()
-    );
-    println!(
-        "Part 2: {}",
-        lines.iter().map(|s| calculate_2(&s)).sum::<i64>()
-    );
+    let mut bad: BTreeMap<usize, Vec<Error>> = BTreeMap::new();
+    for (part, table) in [
+        (1, OperatorTable::left_to_right()),
+        (2, OperatorTable::addition_first()),
+    ] {
+        let (values, errors) = evaluate_lines(lines, &table);
+        println!("Part {}: {}", part, values.iter().sum::<i64>());
+
+        for (line, errors) in errors {
+            let seen = bad.entry(line).or_default();
+            for e in errors {
+                if !seen.contains(&e) {
+                    seen.push(e);
+                }
+            }
+        }
+    }
+
+    if !bad.is_empty() {
+        println!("{} bad lines skipped:", bad.len());
+        for (line, errors) in &bad {
+            for e in errors {
+                println!("line {}: {}", line, caret(&lines[line - 1], e));
+            }
+        }
+    }
 
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::day_18::lexer::tokenize_with;
+    use crate::day_18::number::Number;
+    use crate::day_18::parser::parse;
     use crate::day_18::*;
+    use num::{BigInt, BigRational};
+
+    fn evaluate_as<N: Number>(s: &str, table: &OperatorTable) -> Result<N, Error> {
+        let tokens = tokenize_with(s, !N::BOUNDED)?;
+        Ok(eval(&parse(&tokens, table)?, &Env::new())?)
+    }
+
+    fn evaluate(s: &str, table: &OperatorTable) -> Result<i64, Error> {
+        evaluate_as(s, table)
+    }
+
+    fn calculate(s: &str) -> Result<i64, Error> {
+        evaluate(s, &OperatorTable::left_to_right())
+    }
+
+    fn calculate_2(s: &str) -> Result<i64, Error> {
+        evaluate(s, &OperatorTable::addition_first())
+    }
 
     #[test]
     fn test() {
-        assert_eq!(71, calculate("1 + 2 * 3 + 4 * 5 + 6"));
-        assert_eq!(26, calculate("2 * 3 + (4 * 5)"));
-        assert_eq!(437, calculate("5 + (8 * 3 + 9 + 3 * 4 * 3)"));
+        assert_eq!(Ok(71), calculate("1 + 2 * 3 + 4 * 5 + 6"));
+        assert_eq!(Ok(26), calculate("2 * 3 + (4 * 5)"));
+        assert_eq!(Ok(437), calculate("5 + (8 * 3 + 9 + 3 * 4 * 3)"));
         assert_eq!(
-            12240,
+            Ok(12240),
             calculate("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))")
         );
         assert_eq!(
-            13632,
+            Ok(13632),
             calculate("((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2")
         );
 
-        assert_eq!(51, calculate_2("1 + (2 * 3) + (4 * (5 + 6))"));
-        assert_eq!(46, calculate_2("2 * 3 + (4 * 5)"));
-        assert_eq!(1445, calculate_2("5 + (8 * 3 + 9 + 3 * 4 * 3)"));
+        assert_eq!(Ok(51), calculate_2("1 + (2 * 3) + (4 * (5 + 6))"));
+        assert_eq!(Ok(46), calculate_2("2 * 3 + (4 * 5)"));
+        assert_eq!(Ok(1445), calculate_2("5 + (8 * 3 + 9 + 3 * 4 * 3)"));
         assert_eq!(
-            669060,
+            Ok(669060),
             calculate_2("5 * 9 * (7 * 3 * 3 + 9 * 3 + (8 + 6 * 4))")
         );
         assert_eq!(
-            23340,
+            Ok(23340),
             calculate_2("((2 + 4 * 9) * (6 + 9 * 8 + 6) + 6) + 2 + 4 * 2")
         );
+
+        assert_eq!(Ok(15), calculate("12 + 3"));
+        assert_eq!(Ok(-80), calculate("-20*4"));
+        assert_eq!(Ok(-4), calculate_2("2 * (10 + -11) + -1 * 1"));
+        assert_eq!(
+            Err(Error::Lex(LexError::UnexpectedChar { ch: '$', column: 5 })),
+            calculate("1 + $")
+        );
+        assert_eq!(
+            Err(Error::Eval(EvalError::UndefinedVariable("x".to_string()))),
+            calculate("1 + x")
+        );
+
+        assert_eq!(
+            Ok(11),
+            evaluate("1 + 2 * 3 + 4", &OperatorTable::normal_math())
+        );
+    }
+
+    #[test]
+    fn lines() {
+        let lines = ["1 + 2", "(1 + 2", "3 * 4", "1 +* 2)", "1 / 0", "", "2 $ 3"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let (values, errors) = evaluate_lines(&lines, &OperatorTable::addition_first());
+
+        assert_eq!(vec![3, 12], values);
+        assert_eq!(vec![&2, &4, &5, &6, &7], errors.keys().collect::<Vec<_>>());
+        assert_eq!(2, errors[&4].len());
+        assert_eq!(
+            "error: unexpected CloseParen at column 7\n  1 +* 2)\n        ^",
+            caret(&lines[3], &errors[&4][1])
+        );
+        assert_eq!(vec![Error::Eval(EvalError::DivisionByZero)], errors[&5]);
+        assert_eq!(
+            vec![Error::Lex(LexError::UnexpectedChar { ch: '$', column: 3 })],
+            errors[&7]
+        );
+    }
+
+    #[test]
+    fn operators() {
+        let math = OperatorTable::normal_math();
+        assert_eq!(Ok(-7), evaluate("1 - 2 * 4", &math));
+        assert_eq!(Ok(-1), calculate("1 - 2 * 4 / 3"));
+        assert_eq!(Ok(4), calculate_2("-(2) * -(-2 + 3) * 2"));
+        assert_eq!(Ok(2), evaluate("17 % 5 ^ 2 / 8", &math));
+        assert_eq!(Ok(-1), evaluate("-7 % 3", &math));
+        assert_eq!(Ok(512), evaluate("2 ^ 3 ^ 2", &math));
+        assert_eq!(Ok(64), calculate("2 ^ 3 ^ 2"));
+        assert_eq!(Ok(-4), evaluate("-(2) ^ 2", &math));
+        assert_eq!(Ok(4), evaluate("-2 ^ 2", &math));
+        assert_eq!(Ok(1), evaluate("0 ^ 0", &math));
+
+        assert_eq!(
+            Err(Error::Eval(EvalError::DivisionByZero)),
+            calculate("1 / (2 - 2)")
+        );
+        assert_eq!(
+            Err(Error::Eval(EvalError::DivisionByZero)),
+            calculate("1 % 0")
+        );
+        assert_eq!(
+            Err(Error::Eval(EvalError::NegativeExponent)),
+            calculate("2 ^ -1")
+        );
+        assert_eq!(Err(Error::Eval(EvalError::Overflow)), calculate("2 ^ 63"));
+        assert_eq!(
+            Err(Error::Eval(EvalError::Overflow)),
+            calculate("-9223372036854775808 / -1")
+        );
+        assert_eq!(
+            Err(Error::Eval(EvalError::Overflow)),
+            calculate("-(-9223372036854775808)")
+        );
+        assert_eq!(
+            Err(Error::Eval(EvalError::Overflow)),
+            calculate("3037000500 * 3037000500")
+        );
+        assert_eq!(
+            Ok(BigInt::from(3037000500u64 * 3037000500)),
+            evaluate_as("3037000500 * 3037000500", &math)
+        );
+        assert_eq!(
+            Ok(BigRational::new(BigInt::from(3), BigInt::from(2))),
+            evaluate_as("1 + 1 / 2", &math)
+        );
+        assert_eq!(
+            Ok(BigInt::from(10).pow(20)),
+            evaluate_as("99999999999999999999 + 1", &math)
+        );
+        assert!(matches!(
+            calculate("99999999999999999999 + 1"),
+            Err(Error::Lex(LexError::NumberTooLarge { .. }))
+        ));
     }
 }
@@ -0,0 +1,49 @@
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+
+use crate::day_18::bytecode::Program;
+use crate::day_18::eval::{eval, Env, EvalError};
+use crate::day_18::lexer::tokenize;
+use crate::day_18::parser::{parse, OperatorTable};
+use crate::day_18::Error;
+use crate::utils::time;
+
+pub fn bench() -> Result<(), std::io::Error> {
+    let file = File::open("18_input")?;
+    let reader = BufReader::new(file);
+    let lines = reader.lines().map(|s| s.unwrap()).collect::<Vec<_>>();
+    let env: Env = Env::new();
+
+    for (part, table) in [
+        (1, OperatorTable::left_to_right()),
+        (2, OperatorTable::addition_first()),
+    ] {
+        let nodes = lines
+            .iter()
+            .map(|s| Ok(parse(&tokenize(s)?, &table)?))
+            .collect::<Result<Vec<_>, Error>>()
+            .unwrap();
+        let programs = nodes.iter().map(Program::compile).collect::<Vec<_>>();
+
+        let (old, old_time) = time(100, || {
+            nodes
+                .iter()
+                .map(|node| eval(node, &env))
+                .sum::<Result<i64, EvalError>>()
+        });
+        let (new, new_time) = time(100, || {
+            programs
+                .iter()
+                .map(|program| program.run(&env))
+                .sum::<Result<i64, EvalError>>()
+        });
+        assert_eq!(old, new);
+        println!(
+            "Part {}: tree {:?}, bytecode {:?}",
+            part, old_time, new_time
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use num::BigInt;
+
+use crate::day_18::eval::{call, Env, EvalError};
+use crate::day_18::number::Number;
+use crate::day_18::{BinOp, Node};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Op {
+    Push(i64),
+    Const(u32),
+    Load(u32),
+    Neg,
+    Apply(BinOp),
+    Call(u32, u32),
+}
+
+// Variable and function names are stored once in `names` and referred to by
+// index, so the code itself stays small and Copy. Literals too large for an
+// i64 are kept in `constants` the same way.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+    pub code: Vec<Op>,
+    pub names: Vec<String>,
+    pub constants: Vec<BigInt>,
+    depth: usize,
+}
+
+impl Program {
+    pub fn compile(node: &Node) -> Program {
+        let mut program = Program {
+            code: Vec::new(),
+            names: Vec::new(),
+            constants: Vec::new(),
+            depth: 0,
+        };
+        program.depth = program.emit(node, 0);
+        program
+    }
+
+    fn name(&mut self, name: &str) -> u32 {
+        let index = match self.names.iter().position(|n| n == name) {
+            Some(index) => index,
+            None => {
+                self.names.push(name.to_string());
+                self.names.len() - 1
+            }
+        };
+        index as u32
+    }
+
+    // Returns the deepest the stack gets while running the emitted code,
+    // given `height` values already on it.
+    fn emit(&mut self, node: &Node, height: usize) -> usize {
+        match node {
+            Node::Number(n) => {
+                self.code.push(Op::Push(*n));
+                height + 1
+            }
+            Node::BigNumber(n) => {
+                self.constants.push(n.clone());
+                self.code.push(Op::Const(self.constants.len() as u32 - 1));
+                height + 1
+            }
+            Node::Variable(name) => {
+                let index = self.name(name);
+                self.code.push(Op::Load(index));
+                height + 1
+            }
+            Node::Neg(a) => {
+                let depth = self.emit(a, height);
+                self.code.push(Op::Neg);
+                depth
+            }
+            Node::Binary(op, a, b) => {
+                let depth = self.emit(a, height).max(self.emit(b, height + 1));
+                self.code.push(Op::Apply(*op));
+                depth
+            }
+            Node::Call(name, args) => {
+                let depth = args
+                    .iter()
+                    .enumerate()
+                    .map(|(i, arg)| self.emit(arg, height + i))
+                    .max()
+                    .unwrap_or(height + 1);
+                let index = self.name(name);
+                self.code.push(Op::Call(index, args.len() as u32));
+                depth
+            }
+        }
+    }
+
+    pub fn run<N: Number>(&self, env: &Env<N>) -> Result<N, EvalError> {
+        let mut stack: Vec<N> = Vec::with_capacity(self.depth);
+
+        for op in &self.code {
+            let value = match *op {
+                Op::Push(n) => N::from_i64(n),
+                Op::Const(index) => N::from_big(&self.constants[index as usize])?,
+                Op::Load(index) => {
+                    let name = &self.names[index as usize];
+                    env.vars
+                        .get(name)
+                        .cloned()
+                        .ok_or_else(|| EvalError::UndefinedVariable(name.clone()))?
+                }
+                Op::Neg => stack.pop().unwrap().neg()?,
+                Op::Apply(op) => {
+                    let b = stack.pop().unwrap();
+                    let a = stack.pop().unwrap();
+                    N::apply(op, &a, &b)?
+                }
+                Op::Call(index, argc) => {
+                    let args = stack.split_off(stack.len() - argc as usize);
+                    call(&self.names[index as usize], args, env, 0)?
+                }
+            };
+            stack.push(value);
+        }
+
+        Ok(stack.pop().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    use crate::day_18::bytecode::*;
+    use crate::day_18::eval::eval;
+    use crate::day_18::lexer::tokenize_with;
+    use crate::day_18::parser::{parse, OperatorTable};
+
+    fn compile(s: &str, table: &OperatorTable) -> (Node, Program) {
+        let node = parse(&tokenize_with(s, true).unwrap(), table).unwrap();
+        let program = Program::compile(&node);
+        (node, program)
+    }
+
+    #[test]
+    fn test() {
+        let math = OperatorTable::normal_math();
+        let (_, program) = compile("x * (y + -x) ^ f(2, x)", &math);
+        assert_eq!(
+            vec![
+                Op::Load(0),
+                Op::Load(1),
+                Op::Load(0),
+                Op::Neg,
+                Op::Apply(BinOp::Add),
+                Op::Push(2),
+                Op::Load(0),
+                Op::Call(2, 2),
+                Op::Apply(BinOp::Pow),
+                Op::Apply(BinOp::Mul),
+            ],
+            program.code
+        );
+        assert_eq!(vec!["x", "y", "f"], program.names);
+        assert_eq!(4, program.depth);
+
+        let mut env: Env = Env::new();
+        assert_eq!(
+            Err(EvalError::UndefinedVariable("x".to_string())),
+            program.run(&env)
+        );
+        env.run("x = 3", &math).unwrap();
+        env.run("y = 5", &math).unwrap();
+        env.run("f(a, b) = a * b - 4", &math).unwrap();
+        assert_eq!(Ok(12), program.run(&env));
+
+        let (_, program) = compile("2 ^ 62 * 2", &math);
+        assert_eq!(Err(EvalError::Overflow), program.run(&Env::<i64>::new()));
+
+        let (_, program) = compile("99999999999999999999 + 1", &math);
+        assert_eq!(
+            vec![Op::Const(0), Op::Push(1), Op::Apply(BinOp::Add)],
+            program.code
+        );
+        assert_eq!(
+            Ok("100000000000000000000".parse().unwrap()),
+            program.run(&Env::<BigInt>::new())
+        );
+        assert_eq!(Err(EvalError::Overflow), program.run(&Env::<i64>::new()));
+    }
+
+    #[test]
+    fn input() {
+        let file = File::open("18_input").unwrap();
+        let lines = BufReader::new(file)
+            .lines()
+            .map(|s| s.unwrap())
+            .collect::<Vec<_>>();
+
+        for table in [
+            OperatorTable::left_to_right(),
+            OperatorTable::addition_first(),
+        ] {
+            let env: Env = Env::new();
+            for line in &lines {
+                let (node, program) = compile(line, &table);
+                assert_eq!(eval(&node, &env), program.run(&env), "{}", line);
+            }
+        }
+    }
+}
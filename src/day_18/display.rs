@@ -0,0 +1,187 @@
+use std::fmt;
+
+use num::Signed;
+
+use crate::day_18::{BinOp, Node, Statement};
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::Pow => "^",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+fn join<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T], sep: &str) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, "{}", sep)?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+// Every operator application gets its own parentheses, so the output shows
+// exactly how the expression was grouped by the operator table.
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Node::Number(n) if *n < 0 => write!(f, "({})", n),
+            Node::Number(n) => write!(f, "{}", n),
+            Node::BigNumber(n) if n.is_negative() => write!(f, "({})", n),
+            Node::BigNumber(n) => write!(f, "{}", n),
+            Node::Variable(name) => write!(f, "{}", name),
+            Node::Neg(a) => write!(f, "(-{})", a),
+            Node::Binary(op, a, b) => write!(f, "({} {} {})", a, op, b),
+            Node::Call(name, args) => {
+                write!(f, "{}(", name)?;
+                join(f, args, ", ")?;
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Expr(node) => write!(f, "{}", node),
+            Statement::Assign(name, node) => write!(f, "{} = {}", name, node),
+            Statement::Function(name, params, body) => {
+                write!(f, "{}(", name)?;
+                join(f, params, ", ")?;
+                write!(f, ") = {}", body)
+            }
+        }
+    }
+}
+
+pub struct SExpr<'a>(&'a Node);
+
+impl<'a> fmt::Display for SExpr<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Node::Number(n) => write!(f, "{}", n),
+            Node::BigNumber(n) => write!(f, "{}", n),
+            Node::Variable(name) => write!(f, "{}", name),
+            Node::Neg(a) => write!(f, "(- {})", SExpr(a)),
+            Node::Binary(op, a, b) => write!(f, "({} {} {})", op, SExpr(a), SExpr(b)),
+            Node::Call(name, args) => {
+                write!(f, "({}", name)?;
+                for arg in args {
+                    write!(f, " {}", SExpr(arg))?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Node {
+    pub fn sexpr(&self) -> SExpr<'_> {
+        SExpr(self)
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Node::Number(n) => n.to_string(),
+            Node::BigNumber(n) => n.to_string(),
+            Node::Variable(name) => name.clone(),
+            Node::Neg(_) => "neg".to_string(),
+            Node::Binary(op, _, _) => op.to_string(),
+            Node::Call(name, _) => format!("{}()", name),
+        }
+    }
+
+    fn children(&self) -> Vec<&Node> {
+        match self {
+            Node::Number(_) | Node::BigNumber(_) | Node::Variable(_) => vec![],
+            Node::Neg(a) => vec![a],
+            Node::Binary(_, a, b) => vec![a, b],
+            Node::Call(_, args) => args.iter().collect(),
+        }
+    }
+
+    fn draw(&self, prefix: &str, out: &mut String) {
+        let children = self.children();
+        for (i, child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            out.push_str(prefix);
+            out.push_str(if last { "└── " } else { "├── " });
+            out.push_str(&child.label());
+            out.push('\n');
+            child.draw(
+                &format!("{}{}", prefix, if last { "    " } else { "│   " }),
+                out,
+            );
+        }
+    }
+
+    pub fn tree(&self) -> String {
+        let mut out = self.label();
+        out.push('\n');
+        self.draw("", &mut out);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_18::lexer::tokenize;
+    use crate::day_18::parser::{parse, parse_statement, OperatorTable};
+
+    #[test]
+    fn test() {
+        let tokens = tokenize("1 + 2 * 3 + -(4) ^ f(x, 5)").unwrap();
+        let left = parse(&tokens, &OperatorTable::left_to_right()).unwrap();
+        let addition = parse(&tokens, &OperatorTable::addition_first()).unwrap();
+        let math = parse(&tokens, &OperatorTable::normal_math()).unwrap();
+
+        assert_eq!("((((1 + 2) * 3) + (-4)) ^ f(x, 5))", left.to_string());
+        assert_eq!("((1 + 2) * (3 + (-(4 ^ f(x, 5)))))", addition.to_string());
+        assert_eq!("((1 + (2 * 3)) + (-(4 ^ f(x, 5))))", math.to_string());
+
+        assert_eq!(
+            "(+ (+ 1 (* 2 3)) (- (^ 4 (f x 5))))",
+            math.sexpr().to_string()
+        );
+        assert_eq!(
+            "\
++
+├── +
+│   ├── 1
+│   └── *
+│       ├── 2
+│       └── 3
+└── neg
+    └── ^
+        ├── 4
+        └── f()
+            ├── x
+            └── 5
+",
+            math.tree()
+        );
+
+        let table = OperatorTable::normal_math();
+        assert_eq!(
+            "f(a, b) = ((a * b) + 1)",
+            parse_statement(&tokenize("f(a,b)=a*b+1").unwrap(), &table)
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "x = (-(-3))",
+            parse_statement(&tokenize("x = -(-3)").unwrap(), &table)
+                .unwrap()
+                .to_string()
+        );
+    }
+}
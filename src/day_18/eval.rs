@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::day_18::lexer::tokenize_with;
+use crate::day_18::number::Number;
+use crate::day_18::parser::{parse_statement, OperatorTable};
+use crate::day_18::{Error, Node, Statement};
+
+const MAX_CALL_DEPTH: usize = 256;
+
+#[derive(Debug, PartialEq)]
+pub enum EvalError {
+    DivisionByZero,
+    NegativeExponent,
+    FractionalExponent,
+    Overflow,
+    UndefinedVariable(String),
+    UndefinedFunction(String),
+    WrongArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    RecursionLimit(String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvalError::DivisionByZero => write!(f, "division by zero"),
+            EvalError::NegativeExponent => write!(f, "negative exponent"),
+            EvalError::FractionalExponent => write!(f, "fractional exponent"),
+            EvalError::Overflow => write!(f, "arithmetic overflow"),
+            EvalError::UndefinedVariable(name) => write!(f, "undefined variable {}", name),
+            EvalError::UndefinedFunction(name) => write!(f, "undefined function {}", name),
+            EvalError::WrongArgumentCount {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} takes {} arguments but {} were given",
+                name, expected, found
+            ),
+            EvalError::RecursionLimit(name) => {
+                write!(f, "recursion limit reached when calling {}", name)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Function {
+    pub params: Vec<String>,
+    pub body: Node,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Env<N = i64> {
+    pub vars: HashMap<String, N>,
+    pub functions: HashMap<String, Function>,
+}
+
+impl<N> Default for Env<N> {
+    fn default() -> Env<N> {
+        Env {
+            vars: HashMap::new(),
+            functions: HashMap::new(),
+        }
+    }
+}
+
+// Function bodies see their parameters first and fall back to the global
+// variables, which are looked up when the function is called.
+fn eval_scoped<N: Number>(
+    node: &Node,
+    env: &Env<N>,
+    locals: &HashMap<String, N>,
+    depth: usize,
+) -> Result<N, EvalError> {
+    match node {
+        Node::Number(n) => Ok(N::from_i64(*n)),
+        Node::BigNumber(n) => N::from_big(n),
+        Node::Variable(name) => locals
+            .get(name)
+            .or_else(|| env.vars.get(name))
+            .cloned()
+            .ok_or_else(|| EvalError::UndefinedVariable(name.clone())),
+        Node::Neg(a) => eval_scoped(a, env, locals, depth)?.neg(),
+        Node::Binary(op, a, b) => N::apply(
+            *op,
+            &eval_scoped(a, env, locals, depth)?,
+            &eval_scoped(b, env, locals, depth)?,
+        ),
+        Node::Call(name, args) => {
+            let values = args
+                .iter()
+                .map(|arg| eval_scoped(arg, env, locals, depth))
+                .collect::<Result<Vec<_>, _>>()?;
+            call(name, values, env, depth)
+        }
+    }
+}
+
+pub fn call<N: Number>(
+    name: &str,
+    values: Vec<N>,
+    env: &Env<N>,
+    depth: usize,
+) -> Result<N, EvalError> {
+    let function = env
+        .functions
+        .get(name)
+        .ok_or_else(|| EvalError::UndefinedFunction(name.to_string()))?;
+    if function.params.len() != values.len() {
+        return Err(EvalError::WrongArgumentCount {
+            name: name.to_string(),
+            expected: function.params.len(),
+            found: values.len(),
+        });
+    }
+    if depth >= MAX_CALL_DEPTH {
+        return Err(EvalError::RecursionLimit(name.to_string()));
+    }
+
+    let scope = function.params.iter().cloned().zip(values).collect();
+    eval_scoped(&function.body, env, &scope, depth + 1)
+}
+
+pub fn eval<N: Number>(node: &Node, env: &Env<N>) -> Result<N, EvalError> {
+    eval_scoped(node, env, &HashMap::new(), 0)
+}
+
+impl<N: Number> Env<N> {
+    pub fn new() -> Env<N> {
+        Env::default()
+    }
+
+    pub fn execute(&mut self, statement: Statement) -> Result<Option<N>, EvalError> {
+        match statement {
+            Statement::Expr(node) => eval(&node, self).map(Some),
+            Statement::Assign(name, node) => {
+                let value = eval(&node, self)?;
+                self.vars.insert(name, value.clone());
+                Ok(Some(value))
+            }
+            Statement::Function(name, params, body) => {
+                self.functions.insert(name, Function { params, body });
+                Ok(None)
+            }
+        }
+    }
+
+    pub fn run(&mut self, s: &str, table: &OperatorTable) -> Result<Option<N>, Error> {
+        let statement = parse_statement(&tokenize_with(s, !N::BOUNDED)?, table)?;
+        Ok(self.execute(statement)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_18::eval::*;
+    use num::{BigInt, BigRational};
+
+    #[test]
+    fn test() {
+        let table = OperatorTable::normal_math();
+        let mut env: Env = Env::new();
+
+        assert_eq!(Ok(Some(9)), env.run("x = 3 * (2 + 1)", &table));
+        assert_eq!(Ok(Some(10)), env.run("x + 1", &table));
+        assert_eq!(Ok(None), env.run("f(a, b) = a * b + 1", &table));
+        assert_eq!(Ok(Some(28)), env.run("f(x, 3)", &table));
+        assert_eq!(Ok(None), env.run("g(x) = f(x, x) - y", &table));
+        assert_eq!(
+            Err(Error::Eval(EvalError::UndefinedVariable("y".to_string()))),
+            env.run("g(2)", &table)
+        );
+        assert_eq!(Ok(Some(1)), env.run("y = 1", &table));
+        // bound when called, so the new value of y is visible
+        assert_eq!(Ok(Some(4)), env.run("g(2)", &table));
+        assert_eq!(Ok(Some(81)), env.run("x = x * x", &table));
+        assert_eq!(Some(&81), env.vars.get("x"));
+
+        assert_eq!(
+            Err(Error::Eval(EvalError::UndefinedFunction("h".to_string()))),
+            env.run("h(1)", &table)
+        );
+        assert_eq!(
+            Err(Error::Eval(EvalError::WrongArgumentCount {
+                name: "f".to_string(),
+                expected: 2,
+                found: 1
+            })),
+            env.run("f(1)", &table)
+        );
+        assert_eq!(Ok(None), env.run("loop(n) = loop(n + 1)", &table));
+        assert_eq!(
+            Err(Error::Eval(EvalError::RecursionLimit("loop".to_string()))),
+            env.run("loop(0)", &table)
+        );
+        assert_eq!(
+            Err(Error::Eval(EvalError::DivisionByZero)),
+            env.run("z = 1 / 0", &table)
+        );
+        assert_eq!(None, env.vars.get("z"));
+    }
+    #[test]
+    fn numbers() {
+        let table = OperatorTable::normal_math();
+
+        let mut env: Env<BigInt> = Env::new();
+        assert_eq!(
+            Ok(Some(
+                "85070591730234615847396907784232501249".parse().unwrap()
+            )),
+            env.run("x = 9223372036854775807 * 9223372036854775807", &table)
+        );
+        assert_eq!(Ok(Some(BigInt::from(-2))), env.run("-7 / 3", &table));
+        assert_eq!(
+            Ok(Some("100000000000000000000".parse().unwrap())),
+            env.run("99999999999999999999 + 1", &table)
+        );
+        assert_eq!(
+            Err(Error::Eval(EvalError::DivisionByZero)),
+            env.run("x % 0", &table)
+        );
+
+        let mut env: Env<BigRational> = Env::new();
+        assert_eq!(Ok(None), env.run("avg(a, b) = (a + b) / 2", &table));
+        assert_eq!(
+            "-7/3",
+            env.run("-7 / 3", &table).unwrap().unwrap().to_string()
+        );
+        assert_eq!(
+            "11/2",
+            env.run("avg(2, 3) + 3", &table)
+                .unwrap()
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "1/8",
+            env.run("2 ^ -3", &table).unwrap().unwrap().to_string()
+        );
+        assert_eq!(
+            Err(Error::Eval(EvalError::FractionalExponent)),
+            env.run("4 ^ (1 / 2)", &table)
+        );
+        assert_eq!(
+            "1/99999999999999999999",
+            env.run("1 / 99999999999999999999", &table)
+                .unwrap()
+                .unwrap()
+                .to_string()
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt;
+
+use num::BigInt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Number(i64),
+    BigNumber(BigInt),
+    Ident(String),
+    Plus,
+    Minus,
+    Times,
+    Slash,
+    Percent,
+    Caret,
+    OpenParen,
+    CloseParen,
+    Comma,
+    Equals,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lexeme {
+    pub token: Token,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LexError {
+    UnexpectedChar { ch: char, column: usize },
+    NumberTooLarge { span: Span },
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::UnexpectedChar { ch, column } => {
+                write!(f, "unexpected character '{}' at column {}", ch, column)
+            }
+            LexError::NumberTooLarge { span } => {
+                write!(f, "number too large at column {}", span.start + 1)
+            }
+        }
+    }
+}
+
+impl LexError {
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::UnexpectedChar { column, .. } => Span {
+                start: column - 1,
+                end: *column,
+            },
+            LexError::NumberTooLarge { span } => *span,
+        }
+    }
+}
+
+// A minus sign directly followed by a digit is a negative literal when it
+// starts an operand, ie. at the start of the expression or after an
+// operator or an opening parenthesis. Otherwise it is a subtraction or a
+// negation, so -2 ^ 2 is 4 but -(2) ^ 2 depends on the operator table.
+fn starts_operand(tokens: &[Lexeme]) -> bool {
+    match tokens.last() {
+        None => true,
+        Some(lexeme) => !matches!(
+            lexeme.token,
+            Token::Number(_) | Token::BigNumber(_) | Token::Ident(_) | Token::CloseParen
+        ),
+    }
+}
+
+pub fn tokenize(s: &str) -> Result<Vec<Lexeme>, LexError> {
+    tokenize_with(s, false)
+}
+
+// Literals too large for an i64 are an error unless `big` is set, in which
+// case they become BigNumber tokens.
+pub fn tokenize_with(s: &str, big: bool) -> Result<Vec<Lexeme>, LexError> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let start = pos;
+        let next_is_digit = chars.get(pos + 1).map(|c| c.is_ascii_digit()) == Some(true);
+        let token = match chars[pos] {
+            c if c.is_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            '+' => Token::Plus,
+            '*' => Token::Times,
+            '/' => Token::Slash,
+            '%' => Token::Percent,
+            '^' => Token::Caret,
+            '(' => Token::OpenParen,
+            ')' => Token::CloseParen,
+            ',' => Token::Comma,
+            '=' => Token::Equals,
+            c if c.is_alphabetic() || c == '_' => {
+                while pos < chars.len() && (chars[pos].is_alphanumeric() || chars[pos] == '_') {
+                    pos += 1;
+                }
+                tokens.push(Lexeme {
+                    token: Token::Ident(chars[start..pos].iter().collect()),
+                    span: Span { start, end: pos },
+                });
+                continue;
+            }
+            c if c.is_ascii_digit() || (c == '-' && next_is_digit && starts_operand(&tokens)) => {
+                pos += 1;
+                while pos < chars.len() && chars[pos].is_ascii_digit() {
+                    pos += 1;
+                }
+                let span = Span { start, end: pos };
+                let literal: String = chars[start..pos].iter().collect();
+                let token = match literal.parse() {
+                    Ok(n) => Token::Number(n),
+                    Err(_) if big => Token::BigNumber(literal.parse().unwrap()),
+                    Err(_) => return Err(LexError::NumberTooLarge { span }),
+                };
+                tokens.push(Lexeme { token, span });
+                continue;
+            }
+            '-' => Token::Minus,
+            ch => {
+                return Err(LexError::UnexpectedChar {
+                    ch,
+                    column: pos + 1,
+                })
+            }
+        };
+
+        pos += 1;
+        tokens.push(Lexeme {
+            token,
+            span: Span { start, end: pos },
+        });
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_18::lexer::*;
+
+    fn lexeme(token: Token, start: usize, end: usize) -> Lexeme {
+        Lexeme {
+            token,
+            span: Span { start, end },
+        }
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!(
+            Ok(vec![
+                lexeme(Token::Number(12), 0, 2),
+                lexeme(Token::Plus, 3, 4),
+                lexeme(Token::Number(3), 5, 6),
+            ]),
+            tokenize("12 + 3")
+        );
+        assert_eq!(
+            Ok(vec![
+                lexeme(Token::Number(-4), 0, 2),
+                lexeme(Token::Times, 2, 3),
+                lexeme(Token::OpenParen, 3, 4),
+                lexeme(Token::Number(-10), 4, 7),
+                lexeme(Token::Plus, 8, 9),
+                lexeme(Token::Number(7), 9, 10),
+                lexeme(Token::CloseParen, 10, 11),
+            ]),
+            tokenize("-4*(-10 +7)")
+        );
+        assert_eq!(Ok(vec![]), tokenize("  \t"));
+        assert_eq!(
+            Ok(vec![
+                lexeme(Token::Ident("f".to_string()), 0, 1),
+                lexeme(Token::OpenParen, 1, 2),
+                lexeme(Token::Ident("a_1".to_string()), 2, 5),
+                lexeme(Token::Comma, 5, 6),
+                lexeme(Token::Number(-2), 7, 9),
+                lexeme(Token::CloseParen, 9, 10),
+                lexeme(Token::Equals, 11, 12),
+                lexeme(Token::Ident("a_1".to_string()), 13, 16),
+                lexeme(Token::Minus, 16, 17),
+                lexeme(Token::Number(1), 17, 18),
+            ]),
+            tokenize("f(a_1, -2) = a_1-1")
+        );
+
+        let err = tokenize("1 + 2 $ 3").unwrap_err();
+        assert_eq!(LexError::UnexpectedChar { ch: '$', column: 7 }, err);
+        assert_eq!("unexpected character '$' at column 7", err.to_string());
+        assert_eq!(
+            Ok(vec![
+                lexeme(Token::Number(1), 0, 1),
+                lexeme(Token::Minus, 2, 3),
+                lexeme(Token::Number(2), 3, 4),
+                lexeme(Token::Slash, 4, 5),
+                lexeme(Token::Minus, 5, 6),
+                lexeme(Token::OpenParen, 6, 7),
+                lexeme(Token::Number(-3), 7, 9),
+                lexeme(Token::CloseParen, 9, 10),
+                lexeme(Token::Percent, 10, 11),
+                lexeme(Token::Number(4), 11, 12),
+                lexeme(Token::Caret, 12, 13),
+                lexeme(Token::Number(5), 13, 14),
+            ]),
+            tokenize("1 -2/-(-3)%4^5")
+        );
+        assert_eq!(
+            Err(LexError::NumberTooLarge {
+                span: Span { start: 4, end: 24 }
+            }),
+            tokenize("1 + 99999999999999999999")
+        );
+        assert_eq!(
+            Ok(vec![
+                lexeme(Token::Number(1), 0, 1),
+                lexeme(Token::Minus, 2, 3),
+                lexeme(
+                    Token::BigNumber("-99999999999999999999".parse().unwrap()),
+                    4,
+                    25
+                ),
+            ]),
+            tokenize_with("1 - -99999999999999999999", true)
+        );
+    }
+}
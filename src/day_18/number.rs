@@ -0,0 +1,184 @@
+use std::fmt;
+
+use num::{BigInt, BigRational, One, Signed, ToPrimitive, Zero};
+
+use crate::day_18::eval::EvalError;
+use crate::day_18::BinOp;
+
+pub trait Number: Clone + fmt::Debug + fmt::Display + PartialEq {
+    // Whether results can overflow, which makes the grouping of a chain like
+    // x + 1 + -1 matter.
+    const BOUNDED: bool;
+
+    fn from_i64(n: i64) -> Self;
+    fn from_big(n: &BigInt) -> Result<Self, EvalError>;
+    fn neg(&self) -> Result<Self, EvalError>;
+    fn apply(op: BinOp, a: &Self, b: &Self) -> Result<Self, EvalError>;
+}
+
+impl Number for i64 {
+    const BOUNDED: bool = true;
+
+    fn from_i64(n: i64) -> i64 {
+        n
+    }
+
+    fn from_big(n: &BigInt) -> Result<i64, EvalError> {
+        n.to_i64().ok_or(EvalError::Overflow)
+    }
+
+    fn neg(&self) -> Result<i64, EvalError> {
+        self.checked_neg().ok_or(EvalError::Overflow)
+    }
+
+    fn apply(op: BinOp, a: &i64, b: &i64) -> Result<i64, EvalError> {
+        let (a, b) = (*a, *b);
+        match op {
+            BinOp::Div | BinOp::Rem if b == 0 => return Err(EvalError::DivisionByZero),
+            BinOp::Pow if b < 0 => return Err(EvalError::NegativeExponent),
+            _ => {}
+        }
+
+        match op {
+            BinOp::Add => a.checked_add(b),
+            BinOp::Sub => a.checked_sub(b),
+            BinOp::Mul => a.checked_mul(b),
+            BinOp::Div => a.checked_div(b),
+            BinOp::Rem => a.checked_rem(b),
+            BinOp::Pow => num::traits::checked_pow(a, b as usize),
+        }
+        .ok_or(EvalError::Overflow)
+    }
+}
+
+impl Number for BigInt {
+    const BOUNDED: bool = false;
+
+    fn from_i64(n: i64) -> BigInt {
+        BigInt::from(n)
+    }
+
+    fn from_big(n: &BigInt) -> Result<BigInt, EvalError> {
+        Ok(n.clone())
+    }
+
+    fn neg(&self) -> Result<BigInt, EvalError> {
+        Ok(-self)
+    }
+
+    fn apply(op: BinOp, a: &BigInt, b: &BigInt) -> Result<BigInt, EvalError> {
+        match op {
+            BinOp::Add => Ok(a + b),
+            BinOp::Sub => Ok(a - b),
+            BinOp::Mul => Ok(a * b),
+            BinOp::Div | BinOp::Rem if b.is_zero() => Err(EvalError::DivisionByZero),
+            BinOp::Div => Ok(a / b),
+            BinOp::Rem => Ok(a % b),
+            BinOp::Pow if b.is_negative() => Err(EvalError::NegativeExponent),
+            BinOp::Pow => b
+                .to_u32()
+                .map(|exponent| a.pow(exponent))
+                .ok_or(EvalError::Overflow),
+        }
+    }
+}
+
+// Division is exact, and negative exponents are fine as long as the base is
+// not zero.
+impl Number for BigRational {
+    const BOUNDED: bool = false;
+
+    fn from_i64(n: i64) -> BigRational {
+        BigRational::from_integer(BigInt::from(n))
+    }
+
+    fn from_big(n: &BigInt) -> Result<BigRational, EvalError> {
+        Ok(BigRational::from_integer(n.clone()))
+    }
+
+    fn neg(&self) -> Result<BigRational, EvalError> {
+        Ok(-self)
+    }
+
+    fn apply(op: BinOp, a: &BigRational, b: &BigRational) -> Result<BigRational, EvalError> {
+        match op {
+            BinOp::Add => Ok(a + b),
+            BinOp::Sub => Ok(a - b),
+            BinOp::Mul => Ok(a * b),
+            BinOp::Div | BinOp::Rem if b.is_zero() => Err(EvalError::DivisionByZero),
+            BinOp::Div => Ok(a / b),
+            BinOp::Rem => Ok(a % b),
+            BinOp::Pow if !b.is_integer() => Err(EvalError::FractionalExponent),
+            BinOp::Pow if a.is_zero() && b.is_negative() => Err(EvalError::DivisionByZero),
+            BinOp::Pow if a.is_one() => Ok(a.clone()),
+            BinOp::Pow => b
+                .to_integer()
+                .to_i32()
+                .map(|exponent| a.pow(exponent))
+                .ok_or(EvalError::Overflow),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_18::eval::EvalError;
+    use crate::day_18::number::*;
+
+    fn rational(n: i64, d: i64) -> BigRational {
+        BigRational::new(BigInt::from(n), BigInt::from(d))
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!(Ok(-7), i64::apply(BinOp::Div, &-15, &2));
+        assert_eq!(
+            Err(EvalError::Overflow),
+            i64::apply(BinOp::Mul, &i64::MAX, &2)
+        );
+
+        let big = BigInt::from(i64::MAX);
+        assert_eq!(
+            Ok("18446744073709551614".parse::<BigInt>().unwrap()),
+            BigInt::apply(BinOp::Mul, &big, &BigInt::from(2))
+        );
+        assert_eq!(
+            Ok(BigInt::from(-7)),
+            BigInt::apply(BinOp::Div, &BigInt::from(-15), &BigInt::from(2))
+        );
+        assert_eq!(
+            Err(EvalError::NegativeExponent),
+            BigInt::apply(BinOp::Pow, &big, &BigInt::from(-1))
+        );
+
+        assert_eq!(
+            Ok(rational(-15, 2)),
+            BigRational::apply(BinOp::Div, &rational(-15, 1), &rational(2, 1))
+        );
+        assert_eq!(
+            Ok(rational(1, 4)),
+            BigRational::apply(BinOp::Pow, &rational(2, 1), &rational(-2, 1))
+        );
+        assert_eq!(
+            Ok(rational(1, 6)),
+            BigRational::apply(BinOp::Rem, &rational(3, 2), &rational(1, 3))
+        );
+        assert_eq!(
+            Err(EvalError::FractionalExponent),
+            BigRational::apply(BinOp::Pow, &rational(2, 1), &rational(1, 2))
+        );
+        assert_eq!(
+            Err(EvalError::DivisionByZero),
+            BigRational::apply(BinOp::Pow, &rational(0, 1), &rational(-1, 1))
+        );
+        assert_eq!(Ok(rational(-1, 3)), rational(1, 3).neg());
+
+        let huge: BigInt = "99999999999999999999".parse().unwrap();
+        assert_eq!(Err(EvalError::Overflow), i64::from_big(&huge));
+        assert_eq!(Ok(huge.clone()), BigInt::from_big(&huge));
+        assert_eq!(
+            Ok(BigRational::from_integer(huge.clone())),
+            BigRational::from_big(&huge)
+        );
+    }
+}
@@ -0,0 +1,714 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::day_18::lexer::{Lexeme, Span, Token};
+use crate::day_18::{BinOp, Node, Statement};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OperatorTable {
+    ops: HashMap<BinOp, (u32, Assoc)>,
+    negation: Option<u32>,
+}
+
+impl OperatorTable {
+    pub fn new() -> OperatorTable {
+        OperatorTable::default()
+    }
+
+    pub fn with(mut self, op: BinOp, precedence: u32, assoc: Assoc) -> OperatorTable {
+        self.set(op, precedence, assoc);
+        self
+    }
+
+    pub fn with_negation(mut self, precedence: u32) -> OperatorTable {
+        self.negation = Some(precedence);
+        self
+    }
+
+    pub fn set(&mut self, op: BinOp, precedence: u32, assoc: Assoc) {
+        self.ops.insert(op, (precedence, assoc));
+    }
+
+    pub fn set_negation(&mut self, precedence: u32) {
+        self.negation = Some(precedence);
+    }
+
+    pub fn get(&self, op: BinOp) -> Option<(u32, Assoc)> {
+        self.ops.get(&op).copied()
+    }
+
+    pub fn negation(&self) -> Option<u32> {
+        self.negation
+    }
+
+    pub fn left_to_right() -> OperatorTable {
+        OperatorTable::new()
+            .with(BinOp::Add, 1, Assoc::Left)
+            .with(BinOp::Sub, 1, Assoc::Left)
+            .with(BinOp::Mul, 1, Assoc::Left)
+            .with(BinOp::Div, 1, Assoc::Left)
+            .with(BinOp::Rem, 1, Assoc::Left)
+            .with(BinOp::Pow, 1, Assoc::Left)
+            .with_negation(2)
+    }
+
+    pub fn addition_first() -> OperatorTable {
+        OperatorTable::new()
+            .with(BinOp::Add, 2, Assoc::Left)
+            .with(BinOp::Sub, 2, Assoc::Left)
+            .with(BinOp::Mul, 1, Assoc::Left)
+            .with(BinOp::Div, 1, Assoc::Left)
+            .with(BinOp::Rem, 1, Assoc::Left)
+            .with(BinOp::Pow, 3, Assoc::Right)
+            .with_negation(3)
+    }
+
+    pub fn normal_math() -> OperatorTable {
+        OperatorTable::new()
+            .with(BinOp::Add, 1, Assoc::Left)
+            .with(BinOp::Sub, 1, Assoc::Left)
+            .with(BinOp::Mul, 2, Assoc::Left)
+            .with(BinOp::Div, 2, Assoc::Left)
+            .with(BinOp::Rem, 2, Assoc::Left)
+            .with(BinOp::Pow, 4, Assoc::Right)
+            .with_negation(3)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    UnexpectedToken { token: Token, span: Span },
+    UnexpectedEnd { span: Span },
+    EmptyExpression { span: Span },
+    UnclosedParen { span: Span },
+    UnsupportedOperator { token: Token, span: Span },
+    DuplicateParameter { name: String, span: Span },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedToken { token, span } => {
+                write!(f, "unexpected {:?} at column {}", token, span.start + 1)
+            }
+            ParseError::UnexpectedEnd { .. } => write!(f, "unexpected end of expression"),
+            ParseError::EmptyExpression { .. } => write!(f, "empty expression"),
+            ParseError::UnclosedParen { span } => {
+                write!(f, "unclosed parenthesis at column {}", span.start + 1)
+            }
+            ParseError::UnsupportedOperator { token, span } => write!(
+                f,
+                "operator {:?} at column {} is not supported in this mode",
+                token,
+                span.start + 1
+            ),
+            ParseError::DuplicateParameter { name, span } => write!(
+                f,
+                "duplicate parameter {} at column {}",
+                name,
+                span.start + 1
+            ),
+        }
+    }
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::UnexpectedEnd { span }
+            | ParseError::EmptyExpression { span }
+            | ParseError::UnclosedParen { span }
+            | ParseError::UnsupportedOperator { span, .. }
+            | ParseError::DuplicateParameter { span, .. } => *span,
+        }
+    }
+}
+
+fn binop(token: &Token) -> Option<BinOp> {
+    match token {
+        Token::Plus => Some(BinOp::Add),
+        Token::Minus => Some(BinOp::Sub),
+        Token::Times => Some(BinOp::Mul),
+        Token::Slash => Some(BinOp::Div),
+        Token::Percent => Some(BinOp::Rem),
+        Token::Caret => Some(BinOp::Pow),
+        _ => None,
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Lexeme],
+    pos: usize,
+    table: &'a OperatorTable,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Lexeme> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek().map(|l| &l.token) == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn unexpected(&self) -> ParseError {
+        match self.peek() {
+            Some(lexeme) => ParseError::UnexpectedToken {
+                token: lexeme.token.clone(),
+                span: lexeme.span,
+            },
+            None => ParseError::UnexpectedEnd { span: self.end() },
+        }
+    }
+
+    fn end(&self) -> Span {
+        let end = self.tokens.last().map(|l| l.span.end).unwrap_or(0);
+        Span { start: end, end }
+    }
+
+    fn primary(&mut self) -> Result<Node, ParseError> {
+        let lexeme = self
+            .peek()
+            .ok_or_else(|| ParseError::UnexpectedEnd { span: self.end() })?;
+        self.pos += 1;
+
+        match lexeme.token {
+            Token::Number(n) => Ok(Node::Number(n)),
+            Token::BigNumber(ref n) => Ok(Node::BigNumber(n.clone())),
+            Token::Minus => {
+                let precedence = self
+                    .table
+                    .negation()
+                    .ok_or(ParseError::UnsupportedOperator {
+                        token: Token::Minus,
+                        span: lexeme.span,
+                    })?;
+                Ok(Node::Neg(Box::new(self.expression(precedence)?)))
+            }
+            Token::Ident(ref name) if self.eat(&Token::OpenParen) => {
+                let args = self.arguments(self.tokens[self.pos - 1].span)?;
+                Ok(Node::Call(name.clone(), args))
+            }
+            Token::Ident(ref name) => Ok(Node::Variable(name.clone())),
+            Token::OpenParen if self.eat(&Token::CloseParen) => Err(ParseError::EmptyExpression {
+                span: Span {
+                    start: lexeme.span.start,
+                    end: self.tokens[self.pos - 1].span.end,
+                },
+            }),
+            Token::OpenParen => {
+                let inner = self.expression(0)?;
+                self.close(lexeme.span)?;
+                Ok(inner)
+            }
+            ref token => Err(ParseError::UnexpectedToken {
+                token: token.clone(),
+                span: lexeme.span,
+            }),
+        }
+    }
+
+    fn close(&mut self, open: Span) -> Result<(), ParseError> {
+        if self.eat(&Token::CloseParen) {
+            Ok(())
+        } else if self.peek().is_none() {
+            Err(ParseError::UnclosedParen { span: open })
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn arguments(&mut self, open: Span) -> Result<Vec<Node>, ParseError> {
+        let mut args = Vec::new();
+        if self.eat(&Token::CloseParen) {
+            return Ok(args);
+        }
+
+        loop {
+            args.push(self.expression(0)?);
+            if !self.eat(&Token::Comma) {
+                self.close(open)?;
+                return Ok(args);
+            }
+        }
+    }
+
+    fn parameters(&mut self, open: Span) -> Result<Vec<String>, ParseError> {
+        let mut params: Vec<String> = Vec::new();
+        if self.eat(&Token::CloseParen) {
+            return Ok(params);
+        }
+
+        loop {
+            match self.peek() {
+                Some(Lexeme {
+                    token: Token::Ident(name),
+                    span,
+                }) => {
+                    if params.contains(name) {
+                        return Err(ParseError::DuplicateParameter {
+                            name: name.clone(),
+                            span: *span,
+                        });
+                    }
+                    params.push(name.clone());
+                    self.pos += 1;
+                }
+                _ => return Err(self.unexpected()),
+            }
+            if !self.eat(&Token::Comma) {
+                self.close(open)?;
+                return Ok(params);
+            }
+        }
+    }
+
+    fn finish<T>(&self, parsed: T) -> Result<T, ParseError> {
+        match self.peek() {
+            None => Ok(parsed),
+            Some(_) => Err(self.unexpected()),
+        }
+    }
+
+    // Precedence climbing: only operators binding at least as tightly as
+    // min_precedence are consumed at this level.
+    fn expression(&mut self, min_precedence: u32) -> Result<Node, ParseError> {
+        let mut left = self.primary()?;
+
+        while let Some(lexeme) = self.peek() {
+            let op = match binop(&lexeme.token) {
+                Some(op) => op,
+                None => break,
+            };
+            let (precedence, assoc) =
+                self.table
+                    .get(op)
+                    .ok_or_else(|| ParseError::UnsupportedOperator {
+                        token: lexeme.token.clone(),
+                        span: lexeme.span,
+                    })?;
+            if precedence < min_precedence {
+                break;
+            }
+
+            self.pos += 1;
+            let next_min = match assoc {
+                Assoc::Left => precedence + 1,
+                Assoc::Right => precedence,
+            };
+            let right = self.expression(next_min)?;
+            left = Node::Binary(op, Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+}
+
+pub fn parse(tokens: &[Lexeme], table: &OperatorTable) -> Result<Node, ParseError> {
+    if tokens.is_empty() {
+        return Err(ParseError::EmptyExpression {
+            span: Span { start: 0, end: 0 },
+        });
+    }
+
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        table,
+    };
+    let node = parser.expression(0)?;
+    parser.finish(node)
+}
+
+fn placeholder(span: Span) -> Lexeme {
+    Lexeme {
+        token: Token::Number(0),
+        span: Span {
+            start: span.start,
+            end: span.start,
+        },
+    }
+}
+
+// Patches the tokens so that parsing gets past `e`, or returns false if
+// there is no sensible patch. Every patch removes a token or supplies one
+// that was missing, so the errors found afterwards are new ones.
+fn repair(tokens: &mut Vec<Lexeme>, e: &ParseError) -> bool {
+    let index = |span: Span| tokens.iter().position(|l| l.span == span);
+    match *e {
+        ParseError::UnexpectedToken { span, .. } => match index(span) {
+            Some(i) => {
+                tokens.remove(i);
+                true
+            }
+            None => false,
+        },
+        ParseError::UnexpectedEnd { span } => {
+            tokens.push(placeholder(span));
+            true
+        }
+        ParseError::UnclosedParen { span } => {
+            let end = tokens.last().map(|l| l.span.end).unwrap_or(span.end);
+            tokens.push(Lexeme {
+                token: Token::CloseParen,
+                span: Span { start: end, end },
+            });
+            true
+        }
+        ParseError::EmptyExpression { span } => match index(Span {
+            start: span.start,
+            end: span.start + 1,
+        }) {
+            Some(i) => {
+                let close = tokens[i + 1].span;
+                tokens.insert(i + 1, placeholder(close));
+                true
+            }
+            None => false,
+        },
+        ParseError::UnsupportedOperator { .. } | ParseError::DuplicateParameter { .. } => false,
+    }
+}
+
+// Like parse, but keeps going after an error to report everything wrong with
+// the expression instead of only the first problem.
+pub fn parse_all(tokens: &[Lexeme], table: &OperatorTable) -> Result<Node, Vec<ParseError>> {
+    let mut tokens = tokens.to_vec();
+    let mut errors = Vec::new();
+
+    loop {
+        match parse(&tokens, table) {
+            Ok(node) if errors.is_empty() => return Ok(node),
+            Ok(_) => return Err(errors),
+            Err(e) => {
+                let repaired = repair(&mut tokens, &e);
+                errors.push(e);
+                if !repaired {
+                    return Err(errors);
+                }
+            }
+        }
+    }
+}
+
+// name(a, b) = ... is a definition rather than a call if the first closing
+// parenthesis is followed by an equals sign.
+fn is_definition(tokens: &[Lexeme]) -> bool {
+    tokens
+        .iter()
+        .position(|l| l.token == Token::CloseParen)
+        .and_then(|close| tokens.get(close + 1))
+        .map(|l| l.token == Token::Equals)
+        .unwrap_or(false)
+}
+
+pub fn parse_statement(tokens: &[Lexeme], table: &OperatorTable) -> Result<Statement, ParseError> {
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        table,
+    };
+
+    let statement = match tokens {
+        [Lexeme {
+            token: Token::Ident(name),
+            ..
+        }, Lexeme {
+            token: Token::Equals,
+            ..
+        }, ..] => {
+            parser.pos = 2;
+            Statement::Assign(name.clone(), parser.expression(0)?)
+        }
+        [Lexeme {
+            token: Token::Ident(name),
+            ..
+        }, Lexeme {
+            token: Token::OpenParen,
+            span,
+        }, ..]
+            if is_definition(tokens) =>
+        {
+            parser.pos = 2;
+            let params = parser.parameters(*span)?;
+            if !parser.eat(&Token::Equals) {
+                return Err(parser.unexpected());
+            }
+            Statement::Function(name.clone(), params, parser.expression(0)?)
+        }
+        _ => Statement::Expr(parser.expression(0)?),
+    };
+
+    parser.finish(statement)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_18::lexer::tokenize;
+    use crate::day_18::parser::*;
+
+    fn num(n: i64) -> Box<Node> {
+        Box::new(Node::Number(n))
+    }
+
+    fn parse_str(s: &str, table: &OperatorTable) -> Result<Node, ParseError> {
+        parse(&tokenize(s).unwrap(), table)
+    }
+
+    fn statement(s: &str) -> Result<Statement, ParseError> {
+        parse_statement(&tokenize(s).unwrap(), &OperatorTable::normal_math())
+    }
+
+    #[test]
+    fn test() {
+        let sum = Node::Binary(BinOp::Add, num(1), num(2));
+        assert_eq!(
+            Ok(Node::Binary(BinOp::Mul, Box::new(sum.clone()), num(3))),
+            parse_str("1 + 2 * 3", &OperatorTable::left_to_right())
+        );
+        assert_eq!(
+            Ok(Node::Binary(BinOp::Mul, Box::new(sum), num(3))),
+            parse_str("1 + 2 * 3", &OperatorTable::addition_first())
+        );
+        assert_eq!(
+            Ok(Node::Binary(
+                BinOp::Add,
+                num(1),
+                Box::new(Node::Binary(BinOp::Mul, num(2), num(3)))
+            )),
+            parse_str("1 + 2 * 3", &OperatorTable::normal_math())
+        );
+
+        let right = OperatorTable::new().with(BinOp::Add, 1, Assoc::Right).with(
+            BinOp::Mul,
+            1,
+            Assoc::Right,
+        );
+        assert_eq!(
+            Ok(Node::Binary(
+                BinOp::Add,
+                num(1),
+                Box::new(Node::Binary(BinOp::Mul, num(2), num(3)))
+            )),
+            parse_str("1 + 2 * 3", &right)
+        );
+
+        let only_add = OperatorTable::new().with(BinOp::Add, 1, Assoc::Left);
+        assert_eq!(
+            Err(ParseError::UnsupportedOperator {
+                token: Token::Times,
+                span: Span { start: 6, end: 7 }
+            }),
+            parse_str("1 + 2 * 3", &only_add)
+        );
+        assert_eq!(
+            Err(ParseError::UnsupportedOperator {
+                token: Token::Minus,
+                span: Span { start: 4, end: 5 }
+            }),
+            parse_str("1 + -(2)", &only_add)
+        );
+
+        let neg = |n: Box<Node>| Box::new(Node::Neg(n));
+        assert_eq!(
+            Ok(Node::Neg(Box::new(Node::Binary(
+                BinOp::Pow,
+                num(2),
+                num(2)
+            )))),
+            parse_str("-(2) ^ 2", &OperatorTable::normal_math())
+        );
+        assert_eq!(
+            Ok(Node::Binary(BinOp::Pow, neg(num(2)), num(2))),
+            parse_str("-(2) ^ 2", &OperatorTable::left_to_right())
+        );
+        assert_eq!(
+            Ok(Node::Binary(
+                BinOp::Pow,
+                num(2),
+                Box::new(Node::Binary(BinOp::Pow, num(3), num(2)))
+            )),
+            parse_str("2 ^ 3 ^ 2", &OperatorTable::normal_math())
+        );
+        assert_eq!(
+            Ok(Node::Binary(
+                BinOp::Sub,
+                Box::new(Node::Binary(BinOp::Sub, num(5), neg(num(2)))),
+                num(1)
+            )),
+            parse_str("5 - -(2) - 1", &OperatorTable::addition_first())
+        );
+
+        let table = OperatorTable::left_to_right();
+        assert_eq!(
+            Err(ParseError::UnclosedParen {
+                span: Span { start: 4, end: 5 }
+            }),
+            parse_str("1 + (2 * 3", &table)
+        );
+        assert_eq!(
+            Err(ParseError::UnexpectedToken {
+                token: Token::CloseParen,
+                span: Span { start: 5, end: 6 }
+            }),
+            parse_str("1 + 2) * 3", &table)
+        );
+        assert_eq!(
+            Err(ParseError::UnexpectedEnd {
+                span: Span { start: 3, end: 3 }
+            }),
+            parse_str("1 +", &table)
+        );
+        assert_eq!(
+            Err(ParseError::UnexpectedToken {
+                token: Token::Times,
+                span: Span { start: 4, end: 5 }
+            }),
+            parse_str("1 + * 2", &table)
+        );
+    }
+    #[test]
+    fn recovery() {
+        let table = OperatorTable::left_to_right();
+        let errors = |s: &str| parse_all(&tokenize(s).unwrap(), &table).unwrap_err();
+
+        assert_eq!(
+            Ok(Node::Binary(BinOp::Add, num(1), num(2))),
+            parse_all(&tokenize("1 + 2").unwrap(), &table)
+        );
+        assert_eq!(
+            vec![ParseError::EmptyExpression {
+                span: Span { start: 0, end: 0 }
+            }],
+            errors("")
+        );
+        assert_eq!(
+            vec![
+                ParseError::UnexpectedToken {
+                    token: Token::Times,
+                    span: Span { start: 4, end: 5 }
+                },
+                ParseError::EmptyExpression {
+                    span: Span { start: 10, end: 12 }
+                },
+                ParseError::UnexpectedToken {
+                    token: Token::CloseParen,
+                    span: Span { start: 13, end: 14 }
+                },
+                ParseError::UnexpectedEnd {
+                    span: Span { start: 25, end: 25 }
+                },
+                ParseError::UnclosedParen {
+                    span: Span { start: 17, end: 18 }
+                },
+            ],
+            errors("1 + * 2 + () ) * (3 + 4 *")
+        );
+        assert_eq!(
+            vec![
+                ParseError::UnexpectedToken {
+                    token: Token::Number(2),
+                    span: Span { start: 2, end: 3 }
+                },
+                ParseError::UnsupportedOperator {
+                    token: Token::Times,
+                    span: Span { start: 8, end: 9 }
+                },
+            ],
+            parse_all(
+                &tokenize("1 2 + 3 * 4").unwrap(),
+                &OperatorTable::new().with(BinOp::Add, 1, Assoc::Left)
+            )
+            .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn statements() {
+        let var = |name: &str| Box::new(Node::Variable(name.to_string()));
+        assert_eq!(
+            Ok(Statement::Assign(
+                "x".to_string(),
+                Node::Binary(BinOp::Mul, num(3), var("y"))
+            )),
+            statement("x = 3 * y")
+        );
+        assert_eq!(
+            Ok(Statement::Function(
+                "f".to_string(),
+                vec!["a".to_string(), "b".to_string()],
+                Node::Binary(
+                    BinOp::Add,
+                    Box::new(Node::Binary(BinOp::Mul, var("a"), var("b"))),
+                    num(1)
+                )
+            )),
+            statement("f(a, b) = a * b + 1")
+        );
+        assert_eq!(
+            Ok(Statement::Function(
+                "one".to_string(),
+                vec![],
+                Node::Number(1)
+            )),
+            statement("one() = 1")
+        );
+        assert_eq!(
+            Ok(Statement::Expr(Node::Binary(
+                BinOp::Sub,
+                Box::new(Node::Call(
+                    "f".to_string(),
+                    vec![Node::Number(1), Node::Call("g".to_string(), vec![])]
+                )),
+                var("x")
+            ))),
+            statement("f(1, g()) - x")
+        );
+
+        assert_eq!(
+            Err(ParseError::DuplicateParameter {
+                name: "a".to_string(),
+                span: Span { start: 5, end: 6 }
+            }),
+            statement("f(a, a) = a")
+        );
+        assert_eq!(
+            Err(ParseError::UnexpectedToken {
+                token: Token::Number(1),
+                span: Span { start: 2, end: 3 }
+            }),
+            statement("f(1) = 2")
+        );
+        assert_eq!(
+            Err(ParseError::UnexpectedToken {
+                token: Token::Equals,
+                span: Span { start: 6, end: 7 }
+            }),
+            statement("x + 1 = 2")
+        );
+        assert_eq!(
+            Err(ParseError::UnclosedParen {
+                span: Span { start: 1, end: 2 }
+            }),
+            statement("f(1, 2")
+        );
+        assert_eq!(
+            Err(ParseError::UnexpectedEnd {
+                span: Span { start: 3, end: 3 }
+            }),
+            statement("x =")
+        );
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use num::{BigInt, BigRational};
+
+use crate::day_18::eval::Env;
+use crate::day_18::lexer::tokenize_with;
+use crate::day_18::number::Number;
+use crate::day_18::parser::{parse, parse_statement, Assoc, OperatorTable};
+use crate::day_18::simplify::simplify;
+use crate::day_18::{caret, BinOp, Error, Statement};
+
+const HELP: &str = "\
+expressions:          1 + 2 * 3, x = 4, f(a, b) = a * b + 1
+:mode                 show the active modes
+:mode <name>          left-to-right, addition-first, normal or both
+:num                  show the number type
+:num <type>           i64, big or rational, each with its own variables
+:set <op> <prec> [left|right]
+                      change an operator of the active modes, op is one of
+                      + - * / % ^ or neg
+:explain [sexpr|tree] <expr>
+                      show how the active modes group an expression, fully
+                      parenthesised, as an s-expression or as a tree
+:simplify <expr>      fold constants and drop identities like x * 1; x * 0
+                      is only dropped when x is a constant, since a
+                      variable could be undefined
+:history              list previous lines, !n runs line n again
+:quit                 exit";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Style {
+    Parens,
+    SExpr,
+    Tree,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Backend {
+    I64,
+    Big,
+    Rational,
+}
+
+impl Backend {
+    fn name(self) -> &'static str {
+        match self {
+            Backend::I64 => "i64",
+            Backend::Big => "big",
+            Backend::Rational => "rational",
+        }
+    }
+}
+
+pub struct Repl {
+    tables: HashMap<String, OperatorTable>,
+    envs: HashMap<String, Env>,
+    big_envs: HashMap<String, Env<BigInt>>,
+    rational_envs: HashMap<String, Env<BigRational>>,
+    backend: Backend,
+    active: Vec<String>,
+    history: Vec<String>,
+}
+
+fn op(symbol: &str) -> Option<BinOp> {
+    match symbol {
+        "+" => Some(BinOp::Add),
+        "-" => Some(BinOp::Sub),
+        "*" => Some(BinOp::Mul),
+        "/" => Some(BinOp::Div),
+        "%" => Some(BinOp::Rem),
+        "^" => Some(BinOp::Pow),
+        _ => None,
+    }
+}
+
+fn run_line<N: Number>(
+    envs: &mut HashMap<String, Env<N>>,
+    name: &str,
+    line: &str,
+    table: &OperatorTable,
+) -> String {
+    let env = envs.entry(name.to_string()).or_default();
+    match env.run(line, table) {
+        Ok(Some(value)) => value.to_string(),
+        Ok(None) => "ok".to_string(),
+        Err(e) => caret(line, &e),
+    }
+}
+
+fn simplify_as<N: Number>(expr: &str, table: &OperatorTable) -> String {
+    let node = tokenize_with(expr, !N::BOUNDED)
+        .map_err(Error::from)
+        .and_then(|tokens| Ok(parse(&tokens, table)?));
+    match node {
+        Ok(node) => simplify::<N>(&node).to_string(),
+        Err(e) => caret(expr, &e),
+    }
+}
+
+impl Default for Repl {
+    fn default() -> Repl {
+        Repl::new()
+    }
+}
+
+impl Repl {
+    pub fn new() -> Repl {
+        let mut tables = HashMap::new();
+        tables.insert("left-to-right".to_string(), OperatorTable::left_to_right());
+        tables.insert(
+            "addition-first".to_string(),
+            OperatorTable::addition_first(),
+        );
+        tables.insert("normal".to_string(), OperatorTable::normal_math());
+
+        Repl {
+            tables,
+            envs: HashMap::new(),
+            big_envs: HashMap::new(),
+            rational_envs: HashMap::new(),
+            backend: Backend::I64,
+            active: vec!["left-to-right".to_string(), "addition-first".to_string()],
+            history: Vec::new(),
+        }
+    }
+
+    fn set_mode(&mut self, name: &str) -> String {
+        if name == "both" {
+            self.active = vec!["left-to-right".to_string(), "addition-first".to_string()];
+        } else if self.tables.contains_key(name) {
+            self.active = vec![name.to_string()];
+        } else {
+            return format!("unknown mode {}", name);
+        }
+        format!("mode: {}", self.active.join(", "))
+    }
+
+    fn set_backend(&mut self, name: &str) -> String {
+        self.backend = match name {
+            "i64" => Backend::I64,
+            "big" => Backend::Big,
+            "rational" => Backend::Rational,
+            _ => return format!("unknown number type {}", name),
+        };
+        format!("numbers: {}", self.backend.name())
+    }
+
+    fn set_operator(&mut self, args: &[&str]) -> String {
+        let (symbol, precedence, assoc) = match args {
+            [symbol, precedence] => (symbol, precedence, Assoc::Left),
+            [symbol, precedence, "left"] => (symbol, precedence, Assoc::Left),
+            [symbol, precedence, "right"] => (symbol, precedence, Assoc::Right),
+            _ => return "usage: :set <op> <prec> [left|right]".to_string(),
+        };
+        let precedence = match precedence.parse() {
+            Ok(precedence) => precedence,
+            Err(_) => return format!("invalid precedence {}", precedence),
+        };
+
+        for name in &self.active {
+            let table = self.tables.get_mut(name).unwrap();
+            match (*symbol, op(symbol)) {
+                ("neg", _) => table.set_negation(precedence),
+                (_, Some(op)) => table.set(op, precedence, assoc),
+                _ => return format!("unknown operator {}", symbol),
+            }
+        }
+        format!("{} set for {}", symbol, self.active.join(", "))
+    }
+
+    fn each_mode<F: FnMut(&str, &mut Repl) -> String>(&mut self, mut f: F) -> String {
+        let both = self.active.len() > 1;
+        let mut res = Vec::new();
+
+        for name in self.active.clone() {
+            let out = f(&name, self);
+            if both {
+                res.push(format!("{}: {}", name, out));
+            } else {
+                res.push(out);
+            }
+        }
+
+        res.join("\n")
+    }
+
+    fn evaluate(&mut self, line: &str) -> String {
+        self.each_mode(|name, repl| {
+            let table = &repl.tables[name];
+            match repl.backend {
+                Backend::I64 => run_line(&mut repl.envs, name, line, table),
+                Backend::Big => run_line(&mut repl.big_envs, name, line, table),
+                Backend::Rational => run_line(&mut repl.rational_envs, name, line, table),
+            }
+        })
+    }
+
+    fn explain(&mut self, args: &str) -> String {
+        let (style, expr) = match args.split_once(char::is_whitespace) {
+            Some(("sexpr", expr)) => (Style::SExpr, expr.trim()),
+            Some(("tree", expr)) => (Style::Tree, expr.trim()),
+            _ => (Style::Parens, args),
+        };
+
+        self.each_mode(|name, repl| {
+            let statement = tokenize_with(expr, repl.backend != Backend::I64)
+                .map_err(Error::from)
+                .and_then(|tokens| Ok(parse_statement(&tokens, &repl.tables[name])?));
+            let statement = match statement {
+                Ok(statement) => statement,
+                Err(e) => return caret(expr, &e),
+            };
+            let (lhs, node) = match &statement {
+                Statement::Expr(node) => (String::new(), node),
+                Statement::Assign(name, node) => (format!("{} = ", name), node),
+                Statement::Function(name, params, body) => {
+                    (format!("{}({}) = ", name, params.join(", ")), body)
+                }
+            };
+            match style {
+                Style::Parens => statement.to_string(),
+                Style::SExpr => format!("{}{}", lhs, node.sexpr()),
+                Style::Tree => format!("{}{}", lhs, node.tree().trim_end()),
+            }
+        })
+    }
+
+    fn simplify(&mut self, expr: &str) -> String {
+        self.each_mode(|name, repl| {
+            let table = &repl.tables[name];
+            match repl.backend {
+                Backend::I64 => simplify_as::<i64>(expr, table),
+                Backend::Big => simplify_as::<BigInt>(expr, table),
+                Backend::Rational => simplify_as::<BigRational>(expr, table),
+            }
+        })
+    }
+
+    // Returns None when the session should end.
+    pub fn handle(&mut self, line: &str) -> Option<String> {
+        let line = line.trim();
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        let out = match words.as_slice() {
+            [] => String::new(),
+            [":quit"] | [":q"] => return None,
+            [":help"] => HELP.to_string(),
+            [":mode"] => format!("mode: {}", self.active.join(", ")),
+            [":mode", name] => self.set_mode(name),
+            [":num"] => format!("numbers: {}", self.backend.name()),
+            [":num", name] => self.set_backend(name),
+            [":set", args @ ..] => self.set_operator(args),
+            [":explain", ..] => self.explain(line[":explain".len()..].trim()),
+            [":simplify", ..] => self.simplify(line[":simplify".len()..].trim()),
+            [":history"] => self
+                .history
+                .iter()
+                .enumerate()
+                .map(|(i, line)| format!("{:>4}  {}", i + 1, line))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            [cmd, ..] if cmd.starts_with(':') => format!("unknown command {}", cmd),
+            _ if line.starts_with('!') => {
+                let entry = line[1..]
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .and_then(|n| self.history.get(n))
+                    .cloned();
+                match entry {
+                    Some(entry) => return self.handle(&entry),
+                    None => format!("no history entry {}", &line[1..]),
+                }
+            }
+            _ => {
+                self.history.push(line.to_string());
+                self.evaluate(line)
+            }
+        };
+
+        Some(out)
+    }
+}
+
+pub fn repl<R: BufRead, W: Write>(input: R, output: &mut W) -> io::Result<()> {
+    let mut repl = Repl::new();
+    write!(output, "> ")?;
+    output.flush()?;
+
+    for line in input.lines() {
+        match repl.handle(&line?) {
+            Some(out) if out.is_empty() => {}
+            Some(out) => writeln!(output, "{}", out)?,
+            None => return Ok(()),
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+
+    writeln!(output)
+}
+
+pub fn calc() -> io::Result<()> {
+    repl(io::stdin().lock(), &mut io::stdout())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_18::repl::*;
+
+    #[test]
+    fn test() {
+        let input = "\
+2 * 3 + 4
+:explain 2 * 3 + 4
+:explain sexpr 2 * 3 + 4
+:mode normal
+:explain tree f(a) = -a * 2
+:simplify 1 * x + 2 * 3 + 0
+x = 2 ^ 3 ^ 2
+f(a) = a * 2
+f(x) + 1 *
+:mode addition-first
+1 + 2 * 3
+:set * 3
+1 + 2 * 3 + $
+!5
+:history
+:mode bogus
+:num big
+99999999999999999999 + 1
+:simplify 1 + x + -1
+:num rational
+1 / 3 + 1
+:num i64
+99999999999999999999 + 1
+:simplify 1 + x + -1
+:num bogus
+:num
+:quit
+1 + 1
+";
+        let mut output = Vec::new();
+        repl(input.as_bytes(), &mut output).unwrap();
+
+        assert_eq!(
+            "\
+> left-to-right: 10
+addition-first: 14
+> left-to-right: ((2 * 3) + 4)
+addition-first: (2 * (3 + 4))
+> left-to-right: (+ (* 2 3) 4)
+addition-first: (* 2 (+ 3 4))
+> mode: normal
+> f(a) = *
+├── neg
+│   └── a
+└── 2
+> (x + 6)
+> 512
+> ok
+> error: unexpected end of expression
+  f(x) + 1 *
+            ^
+> mode: addition-first
+> 9
+> * set for addition-first
+> error: unexpected character '$' at column 13
+  1 + 2 * 3 + $
+              ^
+> 7
+>    1  2 * 3 + 4
+   2  x = 2 ^ 3 ^ 2
+   3  f(a) = a * 2
+   4  f(x) + 1 *
+   5  1 + 2 * 3
+   6  1 + 2 * 3 + $
+   7  1 + 2 * 3
+> unknown mode bogus
+> numbers: big
+> 100000000000000000000
+> x
+> numbers: rational
+> 1/4
+> numbers: i64
+> error: number too large at column 1
+  99999999999999999999 + 1
+  ^^^^^^^^^^^^^^^^^^^^
+> ((1 + x) + (-1))
+> unknown number type bogus
+> numbers: i64
+> ",
+            String::from_utf8(output).unwrap()
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use crate::day_18::number::Number;
+use crate::day_18::{BinOp, Node};
+
+fn binary(op: BinOp, a: Node, b: Node) -> Node {
+    Node::Binary(op, Box::new(a), Box::new(b))
+}
+
+// Only +, - and * are folded, as those give the same answer with every
+// Number whenever the i64 result fits, while / % and ^ depend on the backend.
+// Subtrees that would fail to evaluate, like 2 ^ 64, are left alone so the
+// error still shows up when the simplified tree is evaluated.
+pub fn fold_constants(node: &Node) -> Node {
+    match node {
+        Node::Number(_) | Node::BigNumber(_) | Node::Variable(_) => node.clone(),
+        Node::Neg(a) => match fold_constants(a) {
+            Node::Number(n) => match n.neg() {
+                Ok(n) => Node::Number(n),
+                Err(_) => Node::Neg(Box::new(Node::Number(n))),
+            },
+            a => Node::Neg(Box::new(a)),
+        },
+        Node::Binary(op, a, b) => match (fold_constants(a), fold_constants(b)) {
+            (Node::Number(a), Node::Number(b)) => match (op, i64::apply(*op, &a, &b)) {
+                (BinOp::Add | BinOp::Sub | BinOp::Mul, Ok(n)) => Node::Number(n),
+                _ => binary(*op, Node::Number(a), Node::Number(b)),
+            },
+            (a, b) => binary(*op, a, b),
+        },
+        Node::Call(name, args) => {
+            Node::Call(name.clone(), args.iter().map(fold_constants).collect())
+        }
+    }
+}
+
+// Whether `node` is a constant that evaluates without error, and so can be
+// dropped without hiding anything.
+fn infallible(node: &Node) -> bool {
+    matches!(fold_constants(node), Node::Number(_))
+}
+
+// --x is kept, since it overflows when x is i64::MIN.
+pub fn simplify_identities(node: &Node) -> Node {
+    match node {
+        Node::Number(_) | Node::BigNumber(_) | Node::Variable(_) => node.clone(),
+        Node::Neg(a) => Node::Neg(Box::new(simplify_identities(a))),
+        Node::Binary(op, a, b) => {
+            let (a, b) = (simplify_identities(a), simplify_identities(b));
+            match (op, &a, &b) {
+                (BinOp::Add, Node::Number(0), _) => b,
+                (BinOp::Add | BinOp::Sub, _, Node::Number(0)) => a,
+                (BinOp::Sub, Node::Number(0), _) => Node::Neg(Box::new(b)),
+                (BinOp::Mul, Node::Number(0), x) | (BinOp::Mul, x, Node::Number(0))
+                    if infallible(x) =>
+                {
+                    Node::Number(0)
+                }
+                (BinOp::Mul, Node::Number(1), _) => b,
+                (BinOp::Mul | BinOp::Div | BinOp::Pow, _, Node::Number(1)) => a,
+                (BinOp::Pow, x, Node::Number(0)) if infallible(x) => Node::Number(1),
+                _ => binary(*op, a, b),
+            }
+        }
+        Node::Call(name, args) => {
+            Node::Call(name.clone(), args.iter().map(simplify_identities).collect())
+        }
+    }
+}
+
+fn operands<N: Number>(op: BinOp, node: &Node, out: &mut Vec<Node>) {
+    match node {
+        Node::Binary(o, a, b) if *o == op => {
+            operands::<N>(op, a, out);
+            operands::<N>(op, b, out);
+        }
+        _ => out.push(flatten::<N>(node)),
+    }
+}
+
+// The operands of a chain in the order it is evaluated, without reaching
+// into parenthesised groups on the right.
+fn spine<N: Number>(op: BinOp, node: &Node, out: &mut Vec<Node>) {
+    match node {
+        Node::Binary(o, a, b) if *o == op => {
+            spine::<N>(op, a, out);
+            out.push(flatten::<N>(b));
+        }
+        _ => out.push(flatten::<N>(node)),
+    }
+}
+
+// Whether acc op b op c can become acc op (b op c) without an overflow
+// appearing or going away: b and c have to move acc the same way.
+fn regroups(op: BinOp, b: i64, c: i64) -> bool {
+    match op {
+        BinOp::Add => (b >= 0) == (c >= 0),
+        _ => b >= 1 && c >= 1,
+    }
+}
+
+fn chain(op: BinOp, terms: Vec<Node>) -> Node {
+    let mut terms = terms.into_iter();
+    let first = terms.next().unwrap();
+    terms.fold(first, |acc, term| binary(op, acc, term))
+}
+
+// Rebuilds chains of + and * as left-leaning chains. When N cannot overflow
+// the variable terms go first and every constant of the chain is combined
+// into one trailing number, so 1 + x + 2 becomes x + 3 whatever the grouping
+// was. Otherwise the order is kept and only neighbouring constants are
+// combined, where that cannot change whether the chain overflows.
+pub fn flatten<N: Number>(node: &Node) -> Node {
+    match node {
+        Node::Number(_) | Node::BigNumber(_) | Node::Variable(_) => node.clone(),
+        Node::Neg(a) => Node::Neg(Box::new(flatten::<N>(a))),
+        Node::Binary(op @ (BinOp::Add | BinOp::Mul), _, _) if N::BOUNDED => {
+            let mut terms = Vec::new();
+            spine::<N>(*op, node, &mut terms);
+
+            let mut res: Vec<Node> = Vec::new();
+            for term in terms {
+                let merged = match (res.last(), &term) {
+                    (Some(Node::Number(b)), Node::Number(c))
+                        if res.len() == 1 || regroups(*op, *b, *c) =>
+                    {
+                        i64::apply(*op, b, c).ok()
+                    }
+                    _ => None,
+                };
+                match merged {
+                    Some(n) => *res.last_mut().unwrap() = Node::Number(n),
+                    None => res.push(term),
+                }
+            }
+            chain(*op, res)
+        }
+        Node::Binary(op @ (BinOp::Add | BinOp::Mul), _, _) => {
+            let mut terms = Vec::new();
+            operands::<N>(*op, node, &mut terms);
+
+            let (mut res, mut constants) = (Vec::new(), Vec::new());
+            for term in terms {
+                match term {
+                    Node::Number(n) => constants.push(n),
+                    term => res.push(term),
+                }
+            }
+
+            let mut rest = constants.iter();
+            if let Some(&first) = rest.next() {
+                match rest.try_fold(first, |acc, n| i64::apply(*op, &acc, n).ok()) {
+                    Some(n) => res.push(Node::Number(n)),
+                    None => res.extend(constants.into_iter().map(Node::Number)),
+                }
+            }
+            chain(*op, res)
+        }
+        Node::Binary(op, a, b) => binary(*op, flatten::<N>(a), flatten::<N>(b)),
+        Node::Call(name, args) => Node::Call(name.clone(), args.iter().map(flatten::<N>).collect()),
+    }
+}
+
+// Simplifies for evaluation with N, giving the same result or error.
+pub fn simplify<N: Number>(node: &Node) -> Node {
+    let mut node = node.clone();
+    loop {
+        let next = simplify_identities(&fold_constants(&flatten::<N>(&node)));
+        if next == node {
+            return node;
+        }
+        node = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num::{BigInt, BigRational};
+
+    use crate::day_18::eval::{eval, Env, EvalError};
+    use crate::day_18::lexer::tokenize;
+    use crate::day_18::parser::{parse, OperatorTable};
+    use crate::day_18::simplify::*;
+
+    fn node(s: &str) -> Node {
+        parse(&tokenize(s).unwrap(), &OperatorTable::normal_math()).unwrap()
+    }
+
+    fn run(pass: fn(&Node) -> Node, s: &str) -> String {
+        pass(&node(s)).to_string()
+    }
+
+    fn same<N: Number>(s: &str, env: &Env<N>) -> Result<N, EvalError> {
+        let node = node(s);
+        let res = eval(&node, env);
+        assert_eq!(res, eval(&simplify::<N>(&node), env), "{}", s);
+        res
+    }
+
+    #[test]
+    fn test() {
+        assert_eq!("(x + 14)", run(fold_constants, "x + 2 * (3 + 4)"));
+        assert_eq!("(-6)", run(fold_constants, "-(2 * 3)"));
+        assert_eq!(
+            "f((x / 0), 2)",
+            run(fold_constants, "f(x / (1 - 1), 1 + 1)")
+        );
+        assert_eq!("(2 ^ 64)", run(fold_constants, "2 ^ 64"));
+
+        assert_eq!("x", run(simplify_identities, "0 + x * 1 - 0"));
+        assert_eq!("0", run(simplify_identities, "(2 + 3) * 0"));
+        assert_eq!("((x + y) * 0)", run(simplify_identities, "(x + y) * 0"));
+        assert_eq!("(-y)", run(simplify_identities, "0 - y"));
+        assert_eq!("f(1, (x ^ 0))", run(simplify_identities, "f(2 ^ 0, x ^ 0)"));
+        assert_eq!("(-(-x))", run(simplify_identities, "--x / 1"));
+        assert_eq!("(x - x)", run(simplify_identities, "x - x"));
+
+        let big = flatten::<BigInt>;
+        assert_eq!("((x + y) + 3)", run(big, "1 + (x + 2) + y"));
+        assert_eq!(
+            "(((x * y) * z) * 24)",
+            run(big, "2 * x * (3 * (y * 4)) * z")
+        );
+        assert_eq!("((x - 1) - 2)", run(big, "x - 1 - 2"));
+        assert_eq!(
+            "((x + 9223372036854775807) + 1)",
+            run(big, "9223372036854775807 + x + 1")
+        );
+
+        let bounded = flatten::<i64>;
+        assert_eq!("((1 + (x + 2)) + y)", run(bounded, "1 + (x + 2) + y"));
+        assert_eq!("(((5 + x) + y) + 3)", run(bounded, "2 + 3 + x + y + 1 + 2"));
+        assert_eq!("(x + 3)", run(bounded, "x + 1 + 2"));
+        assert_eq!("((x + 1) + (-1))", run(simplify::<i64>, "x + 1 + -1"));
+        assert_eq!("x", run(simplify::<BigInt>, "x + 1 + (0 - 1)"));
+        assert_eq!("((x * 6) * 0)", run(bounded, "x * 2 * 3 * 0"));
+
+        assert_eq!(
+            "(x + 6)",
+            run(simplify::<BigInt>, "(1 + x) * (2 - 1) + 0 * 7 + 5")
+        );
+        assert_eq!(
+            "((1 + x) + 5)",
+            run(simplify::<i64>, "(1 + x) * (2 - 1) + 0 * 7 + 5")
+        );
+        assert_eq!("(x * 0)", run(simplify::<i64>, "x * (3 - 3)"));
+    }
+
+    #[test]
+    fn keeps_results() {
+        let rationals: Env<BigRational> = Env::new();
+        let half = BigRational::new(BigInt::from(1), BigInt::from(2));
+        assert_eq!(
+            Ok(BigRational::from_i64(1)),
+            same("1 / 2 + 1 / 2", &rationals)
+        );
+        assert_eq!(Ok(half), same("7 % 3 / 2", &rationals));
+        assert_eq!(
+            Ok(BigRational::from_i64(4)),
+            same("2 ^ -2 * 16", &rationals)
+        );
+        assert_eq!(
+            Ok(BigInt::from(1) << 64),
+            same("2 ^ 64", &Env::<BigInt>::new())
+        );
+
+        let mut env: Env = Env::new();
+        assert_eq!(Err(EvalError::DivisionByZero), same("(1 / 0) * 0", &env));
+        assert_eq!(Err(EvalError::DivisionByZero), same("0 * (1 % 0)", &env));
+        assert_eq!(Err(EvalError::NegativeExponent), same("(2 ^ -1) ^ 0", &env));
+        assert_eq!(
+            Err(EvalError::UndefinedVariable("x".to_string())),
+            same("x * 0", &env)
+        );
+        env.vars.insert("x".to_string(), i64::MIN);
+        assert_eq!(Err(EvalError::Overflow), same("--x", &env));
+        assert_eq!(Err(EvalError::Overflow), same("x * -1 * -1", &env));
+        env.vars.insert("x".to_string(), i64::MAX);
+        assert_eq!(Err(EvalError::Overflow), same("x + 1 + -1", &env));
+        assert_eq!(Err(EvalError::Overflow), same("x * 2 * 0", &env));
+        assert_eq!(Ok(i64::MAX), same("x + -1 + 1", &env));
+        assert_eq!(Err(EvalError::Overflow), same("x + 1 + 1", &env));
+
+        let mut big: Env<BigInt> = Env::new();
+        big.vars.insert("x".to_string(), BigInt::from(i64::MAX));
+        assert_eq!(Ok(BigInt::from(i64::MAX)), same("x + 1 + -1", &big));
+    }
+}
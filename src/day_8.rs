@@ -1,9 +1,20 @@
-use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
 
-#[derive(Debug, PartialEq)]
+pub mod bench;
+pub mod bytecode;
+pub mod cfg;
+#[cfg(test)]
+pub mod fuzz;
+pub mod repair;
+pub mod vm;
+
+use bytecode::Program;
+use vm::{Halt, VmError};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Instruction {
     Nop(i32),
     Acc(i32),
@@ -12,7 +23,7 @@ pub enum Instruction {
 
 impl Instruction {
     pub fn new(from: &str) -> Instruction {
-        let mut split = from.split(" ");
+        let mut split = from.split(' ');
         let instr = split.next().unwrap();
         let count = split.next().unwrap().parse().unwrap();
         match instr {
@@ -24,69 +35,56 @@ impl Instruction {
     }
 }
 
-pub fn simulate(instrs: &Vec<Instruction>) -> Result<i32, i32> {
-    let mut acc = 0;
-    let mut ins = 0;
-
-    let mut visited = HashSet::new();
-    visited.insert(0);
-
-    loop {
-        match instrs.get(ins) {
-            Some(Instruction::Nop(_)) => {
-                ins += 1;
-            }
-            Some(Instruction::Acc(count)) => {
-                acc += count;
-                ins += 1;
-            }
-            Some(Instruction::Jmp(pos)) => {
-                ins = (ins as i32 + pos) as usize;
-            }
-            None => return Err(acc),
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Nop(n) => write!(f, "nop {:+}", n),
+            Instruction::Acc(n) => write!(f, "acc {:+}", n),
+            Instruction::Jmp(n) => write!(f, "jmp {:+}", n),
         }
-
-        if visited.contains(&ins) {
-            break;
-        }
-        visited.insert(ins);
     }
+}
 
-    Ok(acc)
+pub fn simulate(instrs: &[Instruction]) -> Result<Halt<i32>, VmError> {
+    vm::run(instrs)
 }
 
-pub fn loop_simulator(mut instrs: Vec<Instruction>) -> Result<i32, i32> {
-    for i in 0..instrs.len() {
-        match instrs[i] {
-            Instruction::Nop(pos) => {
-                instrs[i] = Instruction::Jmp(pos);
-                simulate(&instrs)?;
-                instrs[i] = Instruction::Nop(pos);
+// The accumulator after the first single flip which makes the program
+// terminate, if there is one.
+pub fn loop_simulator(instrs: Vec<Instruction>) -> Option<i32> {
+    let mut program = Program::compile(&instrs);
+    for i in 0..program.len() {
+        if program.flip(i) {
+            if let Ok(Halt::Terminated(acc)) = program.run() {
+                return Some(acc);
             }
-            Instruction::Jmp(pos) => {
-                instrs[i] = Instruction::Nop(pos);
-                simulate(&instrs)?;
-                instrs[i] = Instruction::Jmp(pos);
-            }
-            _ => {}
+            program.flip(i);
         }
     }
 
-    panic!()
+    None
 }
 
-pub fn eight() -> Result<(), std::io::Error> {
+pub fn input() -> Result<Vec<Instruction>, std::io::Error> {
     let file = File::open("8_input")?;
     let reader = BufReader::new(file);
     let lines: Vec<String> = reader.lines().map(|s| s.unwrap()).collect();
 
-    let instrs = lines
-        .iter()
-        .map(|s| Instruction::new(&s))
-        .collect::<Vec<_>>();
+    Ok(lines.iter().map(|s| Instruction::new(s)).collect())
+}
+
+pub fn eight() -> Result<(), std::io::Error> {
+    let instrs = input()?;
 
-    println!("Part 1: {}", simulate(&instrs).unwrap());
-    println!("Part 2: {}", loop_simulator(instrs).unwrap_err());
+    match simulate(&instrs) {
+        Ok(Halt::Looped(acc)) => println!("Part 1: {}", acc),
+        Ok(Halt::Terminated(acc)) => println!("Part 1: terminated with {}", acc),
+        Err(e) => println!("Part 1: {}", e),
+    }
+    match loop_simulator(instrs) {
+        Some(acc) => println!("Part 2: {}", acc),
+        None => println!("Part 2: no single flip makes the program terminate"),
+    }
 
     Ok(())
 }
@@ -107,10 +105,7 @@ acc -99
 acc +1
 jmp -4
 acc +6";
-        let parsed = ops
-            .lines()
-            .map(|line| Instruction::new(line))
-            .collect::<Vec<_>>();
+        let parsed = ops.lines().map(Instruction::new).collect::<Vec<_>>();
         assert_eq!(
             vec![
                 Nop(0),
@@ -126,10 +121,16 @@ acc +6";
             parsed
         );
 
-        let part_1 = simulate(&parsed).unwrap();
-        assert_eq!(5, part_1);
+        let part_1 = simulate(&parsed);
+        assert_eq!(Ok(Halt::Looped(5)), part_1);
+
+        let part_2 = loop_simulator(parsed);
+        assert_eq!(Some(8), part_2);
 
-        let part_2 = loop_simulator(parsed).unwrap_err();
-        assert_eq!(8, part_2);
+        assert_eq!(
+            Err(VmError::NegativePc { pc: 1, target: -1 }),
+            simulate(&[Nop(0), Jmp(-2)])
+        );
+        assert_eq!(None, loop_simulator(vec![Jmp(0), Jmp(-1)]));
     }
 }
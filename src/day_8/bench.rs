@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+
+use crate::day_8::vm::Halt;
+use crate::day_8::{input, loop_simulator, simulate, Instruction};
+use crate::utils::time;
+
+// The HashSet simulator from before the bytecode VM.
+fn hashed_simulate(instrs: &[Instruction]) -> Halt<i32> {
+    let mut acc = 0;
+    let mut ins = 0;
+
+    let mut visited = HashSet::new();
+    visited.insert(0);
+
+    loop {
+        match instrs.get(ins) {
+            Some(Instruction::Nop(_)) => {
+                ins += 1;
+            }
+            Some(Instruction::Acc(count)) => {
+                acc += count;
+                ins += 1;
+            }
+            Some(Instruction::Jmp(pos)) => {
+                ins = (ins as i32 + pos) as usize;
+            }
+            None => return Halt::Terminated(acc),
+        }
+
+        if visited.contains(&ins) {
+            break;
+        }
+        visited.insert(ins);
+    }
+
+    Halt::Looped(acc)
+}
+
+fn hashed_loop_simulator(mut instrs: Vec<Instruction>) -> Option<i32> {
+    for i in 0..instrs.len() {
+        let original = instrs[i];
+        instrs[i] = match original {
+            Instruction::Nop(pos) => Instruction::Jmp(pos),
+            Instruction::Jmp(pos) => Instruction::Nop(pos),
+            Instruction::Acc(_) => continue,
+        };
+        if let Halt::Terminated(acc) = hashed_simulate(&instrs) {
+            return Some(acc);
+        }
+        instrs[i] = original;
+    }
+
+    None
+}
+
+pub fn bench() -> Result<(), std::io::Error> {
+    let instrs = input()?;
+
+    let (old, old_time) = time(1000, || hashed_simulate(&instrs));
+    let (new, new_time) = time(1000, || simulate(&instrs));
+    assert_eq!(Ok(old), new);
+    println!("Part 1: hashset {:?}, bytecode {:?}", old_time, new_time);
+
+    let (old, old_time) = time(100, || hashed_loop_simulator(instrs.clone()));
+    let (new, new_time) = time(100, || loop_simulator(instrs.clone()));
+    assert_eq!(old, new);
+    println!("Part 2: hashset {:?}, bytecode {:?}", old_time, new_time);
+
+    Ok(())
+}
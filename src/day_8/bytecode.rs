@@ -0,0 +1,138 @@
+use num::{CheckedAdd, Zero};
+
+use crate::day_8::vm::{Halt, VmError};
+use crate::day_8::Instruction;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Opcode {
+    Nop,
+    Acc,
+    Jmp,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program {
+    pub opcodes: Vec<Opcode>,
+    pub operands: Vec<i32>,
+    visited: Vec<u64>,
+}
+
+fn encode(instr: &Instruction) -> (Opcode, i32) {
+    match *instr {
+        Instruction::Nop(n) => (Opcode::Nop, n),
+        Instruction::Acc(n) => (Opcode::Acc, n),
+        Instruction::Jmp(n) => (Opcode::Jmp, n),
+    }
+}
+
+impl Program {
+    pub fn compile(instrs: &[Instruction]) -> Program {
+        let (opcodes, operands) = instrs.iter().map(encode).unzip();
+
+        Program {
+            opcodes,
+            operands,
+            visited: vec![0; instrs.len().div_ceil(64)],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.opcodes.len()
+    }
+
+    pub fn get(&self, pos: usize) -> Instruction {
+        let n = self.operands[pos];
+        match self.opcodes[pos] {
+            Opcode::Nop => Instruction::Nop(n),
+            Opcode::Acc => Instruction::Acc(n),
+            Opcode::Jmp => Instruction::Jmp(n),
+        }
+    }
+
+    pub fn set(&mut self, pos: usize, instr: Instruction) {
+        let (opcode, n) = encode(&instr);
+        self.opcodes[pos] = opcode;
+        self.operands[pos] = n;
+    }
+
+    pub fn flip(&mut self, pos: usize) -> bool {
+        self.opcodes[pos] = match self.opcodes[pos] {
+            Opcode::Nop => Opcode::Jmp,
+            Opcode::Jmp => Opcode::Nop,
+            Opcode::Acc => return false,
+        };
+        true
+    }
+
+    pub fn run<A>(&mut self) -> Result<Halt<A>, VmError>
+    where
+        A: CheckedAdd + Zero + From<i32>,
+    {
+        for word in self.visited.iter_mut() {
+            *word = 0;
+        }
+
+        let mut acc = A::zero();
+        let mut pc = 0;
+
+        while pc < self.opcodes.len() {
+            let (word, bit) = (pc / 64, 1 << (pc % 64));
+            if self.visited[word] & bit != 0 {
+                return Ok(Halt::Looped(acc));
+            }
+            self.visited[word] |= bit;
+
+            let operand = self.operands[pc];
+            let next = match self.opcodes[pc] {
+                Opcode::Nop => pc as i64 + 1,
+                Opcode::Acc => {
+                    acc = acc
+                        .checked_add(&A::from(operand))
+                        .ok_or(VmError::Overflow { pc })?;
+                    pc as i64 + 1
+                }
+                Opcode::Jmp => pc as i64 + operand as i64,
+            };
+
+            if next < 0 {
+                return Err(VmError::NegativePc { pc, target: next });
+            }
+            pc = next as usize;
+        }
+
+        Ok(Halt::Terminated(acc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_8::bytecode::*;
+    use crate::day_8::Instruction::*;
+
+    #[test]
+    fn test() {
+        let instrs = (0..130)
+            .map(|i| if i == 129 { Jmp(-129) } else { Acc(1) })
+            .collect::<Vec<_>>();
+        let mut program = Program::compile(&instrs);
+
+        assert_eq!(130, program.len());
+        assert_eq!(Jmp(-129), program.get(129));
+        assert_eq!(Ok(Halt::Looped(129)), program.run::<i32>());
+        // the visited set is cleared between runs
+        assert_eq!(Ok(Halt::Looped(129)), program.run::<i32>());
+
+        assert!(program.flip(129));
+        assert_eq!(Ok(Halt::Terminated(129)), program.run::<i64>());
+        assert!(!program.flip(0));
+
+        program.set(129, Jmp(-130));
+        assert_eq!(
+            Err(VmError::NegativePc {
+                pc: 129,
+                target: -1
+            }),
+            program.run::<i32>()
+        );
+    }
+}
@@ -0,0 +1,324 @@
+use std::fmt::Write;
+
+use crate::day_8::{input, Instruction};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Edge {
+    Next(usize),
+    Exit,
+    OutOfBounds(i64),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Cfg {
+    pub instrs: Vec<Instruction>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Analysis {
+    pub terminates: bool,
+    pub unreachable: Vec<usize>,
+    pub cycles: Vec<Vec<usize>>,
+    pub out_of_bounds: Vec<(usize, i64)>,
+    pub past_end: Vec<(usize, i64)>,
+}
+
+pub fn target(pos: usize, instr: &Instruction) -> i64 {
+    match instr {
+        Instruction::Jmp(offset) => pos as i64 + *offset as i64,
+        Instruction::Nop(_) | Instruction::Acc(_) => pos as i64 + 1,
+    }
+}
+
+// Like the VM, any jump at or past the end terminates the program, and only
+// negative targets are out of bounds. Cfg::past_end still lists the jumps
+// that overshoot the end.
+pub fn edge(len: usize, target: i64) -> Edge {
+    if target >= len as i64 {
+        Edge::Exit
+    } else if target < 0 {
+        Edge::OutOfBounds(target)
+    } else {
+        Edge::Next(target as usize)
+    }
+}
+
+impl Cfg {
+    pub fn new(instrs: &[Instruction]) -> Cfg {
+        let edges = instrs
+            .iter()
+            .enumerate()
+            .map(|(pos, instr)| edge(instrs.len(), target(pos, instr)))
+            .collect();
+
+        Cfg {
+            instrs: instrs.to_vec(),
+            edges,
+        }
+    }
+
+    fn entry(&self) -> Edge {
+        edge(self.edges.len(), 0)
+    }
+
+    // Every instruction has exactly one successor, so the instructions
+    // reachable from the entry are exactly the ones executed.
+    pub fn trace(&self) -> (Vec<usize>, Edge) {
+        let mut seen = vec![false; self.edges.len()];
+        let mut trace = Vec::new();
+        let mut current = self.entry();
+
+        while let Edge::Next(pos) = current {
+            if seen[pos] {
+                break;
+            }
+            seen[pos] = true;
+            trace.push(pos);
+            current = self.edges[pos];
+        }
+
+        (trace, current)
+    }
+
+    pub fn terminates(&self) -> bool {
+        self.trace().1 == Edge::Exit
+    }
+
+    pub fn unreachable(&self) -> Vec<usize> {
+        let mut reachable = vec![false; self.edges.len()];
+        for pos in self.trace().0 {
+            reachable[pos] = true;
+        }
+
+        (0..self.edges.len())
+            .filter(|pos| !reachable[*pos])
+            .collect()
+    }
+
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        // walk[pos] is the index of the walk which first reached pos
+        let mut walk: Vec<Option<usize>> = vec![None; self.edges.len()];
+        let mut cycles = Vec::new();
+
+        for start in 0..self.edges.len() {
+            let mut current = Edge::Next(start);
+            while let Edge::Next(pos) = current {
+                match walk[pos] {
+                    Some(w) if w == start => {
+                        cycles.push(self.cycle_from(pos));
+                        break;
+                    }
+                    Some(_) => break,
+                    None => {
+                        walk[pos] = Some(start);
+                        current = self.edges[pos];
+                    }
+                }
+            }
+        }
+
+        cycles.sort();
+        cycles
+    }
+
+    fn cycle_from(&self, start: usize) -> Vec<usize> {
+        let mut cycle = vec![start];
+        while let Edge::Next(pos) = self.edges[*cycle.last().unwrap()] {
+            if pos == start {
+                break;
+            }
+            cycle.push(pos);
+        }
+
+        let min = (0..cycle.len()).min_by_key(|i| cycle[*i]).unwrap();
+        cycle.rotate_left(min);
+        cycle
+    }
+
+    pub fn out_of_bounds(&self) -> Vec<(usize, i64)> {
+        self.edges
+            .iter()
+            .enumerate()
+            .filter_map(|(pos, edge)| match edge {
+                Edge::OutOfBounds(target) => Some((pos, *target)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Jumps that land beyond the instruction right after the last one. They
+    // terminate the program, but are most likely a mistake.
+    pub fn past_end(&self) -> Vec<(usize, i64)> {
+        self.instrs
+            .iter()
+            .enumerate()
+            .map(|(pos, instr)| (pos, target(pos, instr)))
+            .filter(|(_, target)| *target > self.instrs.len() as i64)
+            .collect()
+    }
+
+    pub fn analyze(&self) -> Analysis {
+        Analysis {
+            terminates: self.terminates(),
+            unreachable: self.unreachable(),
+            cycles: self.cycles(),
+            out_of_bounds: self.out_of_bounds(),
+            past_end: self.past_end(),
+        }
+    }
+
+    pub fn to_dot(&self) -> String {
+        let unreachable = self.unreachable();
+        let cycle_nodes: Vec<usize> = self.cycles().into_iter().flatten().collect();
+        let node = |edge: &Edge| match edge {
+            Edge::Next(pos) => format!("i{}", pos),
+            Edge::Exit => "exit".to_string(),
+            Edge::OutOfBounds(target) => format!("oob{}", target).replace('-', "_"),
+        };
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph program {{").unwrap();
+        writeln!(dot, "    node [shape=box];").unwrap();
+        writeln!(dot, "    entry [shape=point];").unwrap();
+        writeln!(dot, "    entry -> {};", node(&self.entry())).unwrap();
+
+        for (pos, instr) in self.instrs.iter().enumerate() {
+            let style = if unreachable.contains(&pos) {
+                ", style=dashed, color=gray"
+            } else {
+                ""
+            };
+            writeln!(dot, "    i{} [label=\"{}: {}\"{}];", pos, pos, instr, style).unwrap();
+        }
+
+        writeln!(dot, "    exit [shape=doublecircle];").unwrap();
+        let mut targets: Vec<i64> = self.out_of_bounds().iter().map(|(_, t)| *t).collect();
+        targets.sort_unstable();
+        targets.dedup();
+        for target in targets {
+            writeln!(
+                dot,
+                "    {} [label=\"{}\", shape=octagon, color=red];",
+                node(&Edge::OutOfBounds(target)),
+                target
+            )
+            .unwrap();
+        }
+
+        // Past-end jumps get a node of their own on the way to the exit.
+        let past_end = self.past_end();
+        let mut targets: Vec<i64> = past_end.iter().map(|(_, t)| *t).collect();
+        targets.sort_unstable();
+        targets.dedup();
+        for target in targets {
+            writeln!(
+                dot,
+                "    past{} [label=\"{}\", shape=octagon, color=orange];",
+                target, target
+            )
+            .unwrap();
+            writeln!(dot, "    past{} -> exit [color=orange];", target).unwrap();
+        }
+
+        for (pos, edge) in self.edges.iter().enumerate() {
+            let style = if cycle_nodes.contains(&pos) {
+                " [color=red]"
+            } else {
+                ""
+            };
+            let to = match past_end.iter().find(|(p, _)| *p == pos) {
+                Some((_, target)) => format!("past{}", target),
+                None => node(edge),
+            };
+            writeln!(dot, "    i{} -> {}{};", pos, to, style).unwrap();
+        }
+
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+// `8 cfg` on the puzzle input. The analysis is printed as DOT comments so
+// the output can be piped straight into dot.
+pub fn run() -> Result<(), std::io::Error> {
+    let cfg = Cfg::new(&input()?);
+    let analysis = cfg.analyze();
+
+    println!("// terminates: {}", analysis.terminates);
+    println!("// unreachable: {:?}", analysis.unreachable);
+    println!("// cycles: {:?}", analysis.cycles);
+    println!("// out of bounds: {:?}", analysis.out_of_bounds);
+    println!("// past the end: {:?}", analysis.past_end);
+    print!("{}", cfg.to_dot());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_8::cfg::*;
+    use crate::day_8::Instruction::*;
+
+    #[test]
+    fn analysis() {
+        let instrs = vec![
+            Nop(0),
+            Acc(1),
+            Jmp(4),
+            Acc(3),
+            Jmp(-3),
+            Acc(-99),
+            Acc(1),
+            Jmp(-4),
+            Acc(6),
+        ];
+        let cfg = Cfg::new(&instrs);
+
+        assert_eq!(
+            Analysis {
+                terminates: false,
+                unreachable: vec![5, 8],
+                cycles: vec![vec![1, 2, 6, 7, 3, 4]],
+                out_of_bounds: vec![],
+                past_end: vec![],
+            },
+            cfg.analyze()
+        );
+
+        let dot = cfg.to_dot();
+        assert!(dot.starts_with("digraph program {"));
+        assert!(dot.contains("    i5 [label=\"5: acc -99\", style=dashed, color=gray];"));
+        assert!(dot.contains("    i7 -> i3 [color=red];"));
+        assert!(dot.contains("    i8 -> exit;"));
+
+        let instrs = vec![Nop(0), Jmp(-2), Jmp(0), Jmp(5), Acc(1)];
+        let cfg = Cfg::new(&instrs);
+
+        assert_eq!((vec![0, 1], Edge::OutOfBounds(-1)), cfg.trace());
+        assert_eq!(
+            Analysis {
+                terminates: false,
+                unreachable: vec![2, 3, 4],
+                cycles: vec![vec![2]],
+                out_of_bounds: vec![(1, -1)],
+                past_end: vec![(3, 8)],
+            },
+            cfg.analyze()
+        );
+        let dot = cfg.to_dot();
+        assert!(dot.contains("    i1 -> oob_1;"));
+        assert!(dot.contains("    i3 -> past8;"));
+        assert!(dot.contains("    past8 -> exit [color=orange];"));
+        assert!(dot.contains("    i4 -> exit;"));
+
+        let cfg = Cfg::new(&[Jmp(1000)]);
+        assert!(cfg.terminates());
+        assert_eq!(vec![(0, 1000)], cfg.past_end());
+        assert!(Cfg::new(&[Nop(0), Jmp(1)]).past_end().is_empty());
+
+        assert!(Cfg::new(&[Nop(0), Jmp(2), Acc(1)]).terminates());
+        assert!(Cfg::new(&[]).terminates());
+        assert!(Cfg::new(&[Jmp(5)]).terminates());
+    }
+}
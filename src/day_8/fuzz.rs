@@ -0,0 +1,191 @@
+use std::collections::HashSet;
+
+use crate::day_8::Instruction;
+
+// xorshift64*, good enough for generating test programs without pulling in
+// a dependency
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    pub fn range(&mut self, lo: i32, hi: i32) -> i32 {
+        lo + self.below((hi - lo) as usize) as i32
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+// An offset from pos which can land a few addresses either side of the
+// program, so flipped nops also crash below 0 and exit past the end.
+fn offset(rng: &mut Rng, pos: usize, len: usize) -> i32 {
+    rng.range(-3, len as i32 + 4) - pos as i32
+}
+
+fn terminating(rng: &mut Rng, len: usize) -> Vec<Instruction> {
+    let mut order: Vec<usize> = (1..len).collect();
+    rng.shuffle(&mut order);
+    let mut path = vec![0];
+    path.extend(order.iter().take(rng.below(len)));
+    path.push(len);
+
+    let mut instrs: Vec<Instruction> = (0..len)
+        .map(|pos| match rng.below(3) {
+            0 => Instruction::Acc(rng.range(-50, 50)),
+            1 => Instruction::Nop(offset(rng, pos, len)),
+            _ => Instruction::Jmp(offset(rng, pos, len)),
+        })
+        .collect();
+
+    for step in path.windows(2) {
+        let (from, to) = (step[0], step[1]);
+        instrs[from] = if to == from + 1 && rng.below(2) == 0 {
+            Instruction::Acc(rng.range(-50, 50))
+        } else if to == from + 1 {
+            Instruction::Nop(offset(rng, from, len))
+        } else {
+            Instruction::Jmp(to as i32 - from as i32)
+        };
+    }
+
+    instrs
+}
+
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Looped,
+    Crashed,
+    Terminated(i32),
+}
+
+// A plain HashSet interpreter that shares no code with the VM, so the fuzzer
+// checks the VM instead of agreeing with itself.
+fn interpret(instrs: &[Instruction]) -> Outcome {
+    let mut acc = 0;
+    let mut pc: i64 = 0;
+    let mut visited = HashSet::new();
+
+    loop {
+        if pc < 0 {
+            return Outcome::Crashed;
+        }
+        if pc >= instrs.len() as i64 {
+            return Outcome::Terminated(acc);
+        }
+        if !visited.insert(pc) {
+            return Outcome::Looped;
+        }
+
+        match instrs[pc as usize] {
+            Instruction::Nop(_) => pc += 1,
+            Instruction::Acc(n) => {
+                acc += n;
+                pc += 1;
+            }
+            Instruction::Jmp(n) => pc += n as i64,
+        }
+    }
+}
+
+fn flip(instr: Instruction) -> Option<Instruction> {
+    match instr {
+        Instruction::Nop(n) => Some(Instruction::Jmp(n)),
+        Instruction::Jmp(n) => Some(Instruction::Nop(n)),
+        Instruction::Acc(_) => None,
+    }
+}
+
+// Generates a looping program of len instructions which terminates after
+// flipping the instruction at the returned position.
+pub fn generate(rng: &mut Rng, len: usize) -> (Vec<Instruction>, usize) {
+    assert!(len > 0);
+    loop {
+        let mut instrs = terminating(rng, len);
+        let mut candidates: Vec<usize> = (0..len).collect();
+        rng.shuffle(&mut candidates);
+
+        for pos in candidates {
+            let original = instrs[pos];
+            if let Some(flipped) = flip(original) {
+                instrs[pos] = flipped;
+                if interpret(&instrs) == Outcome::Looped {
+                    return (instrs, pos);
+                }
+                instrs[pos] = original;
+            }
+        }
+    }
+}
+
+// Every single flip which makes the program terminate, with the resulting
+// accumulator.
+pub fn oracle(instrs: &[Instruction]) -> Vec<(usize, i32)> {
+    let mut patched = instrs.to_vec();
+    (0..instrs.len())
+        .filter_map(|pos| {
+            patched[pos] = flip(instrs[pos])?;
+            let res = interpret(&patched);
+            patched[pos] = instrs[pos];
+            match res {
+                Outcome::Terminated(acc) => Some((pos, acc)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_8::cfg::Cfg;
+    use crate::day_8::fuzz::*;
+    use crate::day_8::loop_simulator;
+    use crate::day_8::repair::{repair, Patch, RepairOptions};
+
+    #[test]
+    fn fuzz() {
+        let single = RepairOptions {
+            allow_delete: false,
+            max_patches: Some(1),
+        };
+
+        for seed in 0..1000 {
+            let mut rng = Rng::new(seed);
+            let len = 1 + rng.below(60);
+            let (instrs, broken) = generate(&mut rng, len);
+
+            assert!(!Cfg::new(&instrs).terminates());
+            let fixes = oracle(&instrs);
+            assert!(fixes.iter().any(|(pos, _)| *pos == broken));
+            assert_eq!(Some(fixes[0].1), loop_simulator(instrs.clone()));
+
+            let repairs = repair(&instrs, single).unwrap();
+            assert_eq!(
+                fixes
+                    .iter()
+                    .map(|(pos, acc)| (vec![Patch::Flip(*pos)], *acc))
+                    .collect::<Vec<_>>(),
+                repairs
+                    .into_iter()
+                    .map(|r| (r.patches, r.acc))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+}
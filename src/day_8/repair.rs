@@ -0,0 +1,367 @@
+use std::collections::VecDeque;
+
+use crate::day_8::bytecode::Program;
+use crate::day_8::cfg::{edge, target, Edge};
+use crate::day_8::vm::Halt;
+use crate::day_8::{input, Instruction};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Patch {
+    Flip(usize),
+    Delete(usize),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RepairOptions {
+    pub allow_delete: bool,
+    pub max_patches: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Repair {
+    pub patches: Vec<Patch>,
+    pub acc: i32,
+}
+
+impl Patch {
+    pub fn apply(&self, program: &mut Program) {
+        match *self {
+            Patch::Flip(pos) => {
+                if !program.flip(pos) {
+                    panic!("Cannot flip acc at {}", pos);
+                }
+            }
+            Patch::Delete(pos) => match program.get(pos) {
+                Instruction::Acc(n) => program.set(pos, Instruction::Nop(n)),
+                other => panic!("Cannot delete {} at {}", other, pos),
+            },
+        }
+    }
+}
+
+fn choices(
+    pos: usize,
+    instr: &Instruction,
+    allow_delete: bool,
+) -> Vec<(Instruction, Option<Patch>)> {
+    match *instr {
+        Instruction::Nop(n) => vec![
+            (Instruction::Nop(n), None),
+            (Instruction::Jmp(n), Some(Patch::Flip(pos))),
+        ],
+        Instruction::Jmp(n) => vec![
+            (Instruction::Jmp(n), None),
+            (Instruction::Nop(n), Some(Patch::Flip(pos))),
+        ],
+        Instruction::Acc(n) if allow_delete => vec![
+            (Instruction::Acc(n), None),
+            (Instruction::Nop(n), Some(Patch::Delete(pos))),
+        ],
+        Instruction::Acc(n) => vec![(Instruction::Acc(n), None)],
+    }
+}
+
+// Successors of each instruction, with the patch needed to take that edge.
+// Jumps to negative addresses crash the handheld, so they are left out.
+fn graph(instrs: &[Instruction], allow_delete: bool) -> Vec<Vec<(usize, Option<Patch>)>> {
+    instrs
+        .iter()
+        .enumerate()
+        .map(|(pos, instr)| {
+            choices(pos, instr, allow_delete)
+                .into_iter()
+                .filter_map(
+                    |(patched, patch)| match edge(instrs.len(), target(pos, &patched)) {
+                        Edge::Next(next) => Some((next, patch)),
+                        Edge::Exit => Some((instrs.len(), patch)),
+                        Edge::OutOfBounds(_) => None,
+                    },
+                )
+                .collect()
+        })
+        .collect()
+}
+
+fn distances(graph: &[Vec<(usize, Option<Patch>)>]) -> Vec<Option<usize>> {
+    let mut dist = vec![None; graph.len() + 1];
+    let mut queue = VecDeque::new();
+    dist[0] = Some(0);
+    queue.push_back(0);
+
+    while let Some(pos) = queue.pop_front() {
+        if pos == graph.len() {
+            continue;
+        }
+        let d = dist[pos].unwrap();
+        for (next, patch) in &graph[pos] {
+            let cost = d + if patch.is_some() { 1 } else { 0 };
+            if dist[*next].map(|old| cost < old).unwrap_or(true) {
+                dist[*next] = Some(cost);
+                if patch.is_some() {
+                    queue.push_back(*next);
+                } else {
+                    queue.push_front(*next);
+                }
+            }
+        }
+    }
+
+    dist
+}
+
+fn tight(dist: &[Option<usize>], pos: usize, next: usize, patch: &Option<Patch>) -> bool {
+    let cost = if patch.is_some() { 1 } else { 0 };
+    dist[pos].is_some() && dist[next] == dist[pos].map(|d| d + cost)
+}
+
+// The instructions which reach the exit over edges on a shortest path, found
+// by walking backwards from the exit.
+fn leads_to_exit(graph: &[Vec<(usize, Option<Patch>)>], dist: &[Option<usize>]) -> Vec<bool> {
+    let mut preds = vec![Vec::new(); graph.len() + 1];
+    for (pos, edges) in graph.iter().enumerate() {
+        for (next, patch) in edges {
+            if tight(dist, pos, *next, patch) {
+                preds[*next].push(pos);
+            }
+        }
+    }
+
+    let mut useful = vec![false; graph.len() + 1];
+    let mut stack = vec![graph.len()];
+    useful[graph.len()] = true;
+    while let Some(pos) = stack.pop() {
+        for &prev in &preds[pos] {
+            if !useful[prev] {
+                useful[prev] = true;
+                stack.push(prev);
+            }
+        }
+    }
+
+    useful
+}
+
+// Every terminating run visits each instruction at most once, so the
+// minimal repairs are exactly the simple shortest paths from 0 to the exit.
+// Only instructions which can still reach the exit are followed, otherwise
+// the search blows up on programs with many dead ends.
+fn paths(
+    graph: &[Vec<(usize, Option<Patch>)>],
+    dist: &[Option<usize>],
+    useful: &[bool],
+    pos: usize,
+    on_path: &mut Vec<bool>,
+    patches: &mut Vec<Patch>,
+    res: &mut Vec<Vec<Patch>>,
+) {
+    if pos == graph.len() {
+        let mut found = patches.clone();
+        found.sort();
+        res.push(found);
+        return;
+    }
+
+    on_path[pos] = true;
+    for (next, patch) in &graph[pos] {
+        if on_path[*next] || !useful[*next] || !tight(dist, pos, *next, patch) {
+            continue;
+        }
+        if let Some(patch) = patch {
+            patches.push(*patch);
+        }
+        paths(graph, dist, useful, *next, on_path, patches, res);
+        if patch.is_some() {
+            patches.pop();
+        }
+    }
+    on_path[pos] = false;
+}
+
+// Turning every jmp into a nop always terminates, so without a limit on the
+// number of patches there is always at least one repair.
+pub fn repair(instrs: &[Instruction], options: RepairOptions) -> Option<Vec<Repair>> {
+    let graph = graph(instrs, options.allow_delete);
+    let dist = distances(&graph);
+    let min_patches = dist[instrs.len()]?;
+    if options
+        .max_patches
+        .map(|max| min_patches > max)
+        .unwrap_or(false)
+    {
+        return None;
+    }
+
+    let mut found = Vec::new();
+    let useful = leads_to_exit(&graph, &dist);
+    let mut on_path = vec![false; instrs.len() + 1];
+    paths(
+        &graph,
+        &dist,
+        &useful,
+        0,
+        &mut on_path,
+        &mut vec![],
+        &mut found,
+    );
+    found.sort();
+
+    let program = Program::compile(instrs);
+    Some(
+        found
+            .into_iter()
+            .map(|patches| {
+                let mut patched = program.clone();
+                for patch in &patches {
+                    patch.apply(&mut patched);
+                }
+                match patched.run() {
+                    Ok(Halt::Terminated(acc)) => Repair { patches, acc },
+                    other => panic!("Repair {:?} does not terminate: {:?}", patches, other),
+                }
+            })
+            .collect(),
+    )
+}
+
+const USAGE: &str = "usage: 8 repair [delete] [max <patches>]";
+
+// `8 repair` on the puzzle input.
+pub fn run(args: &[&str]) -> Result<(), std::io::Error> {
+    let mut options = RepairOptions::default();
+    let mut args = args;
+    loop {
+        args = match args {
+            [] => break,
+            ["delete", rest @ ..] => {
+                options.allow_delete = true;
+                rest
+            }
+            ["max", max, rest @ ..] if max.parse::<usize>().is_ok() => {
+                options.max_patches = max.parse().ok();
+                rest
+            }
+            _ => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+        }
+    }
+
+    match repair(&input()?, options) {
+        Some(repairs) => {
+            for repair in repairs {
+                println!("{:?}: acc {}", repair.patches, repair.acc);
+            }
+        }
+        None => println!("no repair within the patch limit"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_8::loop_simulator;
+    use crate::day_8::repair::*;
+    use crate::day_8::Instruction::*;
+
+    #[test]
+    fn test() {
+        let single = RepairOptions {
+            allow_delete: false,
+            max_patches: Some(1),
+        };
+        let deletes = RepairOptions {
+            allow_delete: true,
+            max_patches: None,
+        };
+
+        let instrs = vec![
+            Nop(0),
+            Acc(1),
+            Jmp(4),
+            Acc(3),
+            Jmp(-3),
+            Acc(-99),
+            Acc(1),
+            Jmp(-4),
+            Acc(6),
+        ];
+        assert_eq!(
+            Some(vec![Repair {
+                patches: vec![Patch::Flip(7)],
+                acc: 8
+            }]),
+            repair(&instrs, single)
+        );
+        assert_eq!(repair(&instrs, single), repair(&instrs, deletes));
+
+        let terminating = vec![Nop(0), Acc(2), Jmp(2), Acc(5), Acc(1)];
+        assert_eq!(
+            Some(vec![Repair {
+                patches: vec![],
+                acc: 3
+            }]),
+            repair(&terminating, RepairOptions::default())
+        );
+        assert_eq!(
+            Some(vec![Repair {
+                patches: vec![],
+                acc: 0
+            }]),
+            repair(&[], RepairOptions::default())
+        );
+
+        let instrs = vec![Acc(1), Jmp(0), Acc(2), Jmp(-1), Acc(3)];
+        assert_eq!(None, repair(&instrs, single));
+        assert_eq!(
+            Some(vec![Repair {
+                patches: vec![Patch::Flip(1), Patch::Flip(3)],
+                acc: 6
+            }]),
+            repair(&instrs, deletes)
+        );
+
+        let instrs = vec![Nop(5), Jmp(0), Jmp(-1)];
+        assert_eq!(
+            Some(vec![Repair {
+                patches: vec![Patch::Flip(0)],
+                acc: 0
+            }]),
+            repair(&instrs, single)
+        );
+        assert_eq!(Some(0), loop_simulator(instrs));
+
+        // each block is a dead end which is as cheap to enter as the real
+        // repair, so a search which does not prune them takes 2^k steps
+        let k = 40;
+        let len = 3 * k + 2;
+        let mut instrs = vec![Nop(len)];
+        for _ in 0..k {
+            instrs.extend([Nop(3), Jmp(0), Nop(0)]);
+        }
+        instrs.push(Jmp(0));
+        assert_eq!(
+            Some(vec![Repair {
+                patches: vec![Patch::Flip(0)],
+                acc: 0
+            }]),
+            repair(&instrs, RepairOptions::default())
+        );
+
+        let instrs = vec![Nop(2), Jmp(0), Jmp(0), Acc(1)];
+        assert_eq!(
+            Some(vec![
+                Repair {
+                    patches: vec![Patch::Flip(0), Patch::Flip(2)],
+                    acc: 1
+                },
+                Repair {
+                    patches: vec![Patch::Flip(1), Patch::Flip(2)],
+                    acc: 1
+                },
+            ]),
+            repair(&instrs, RepairOptions::default())
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+
+use num::{CheckedAdd, Zero};
+
+use crate::day_8::bytecode::Program;
+use crate::day_8::Instruction;
+
+#[derive(Debug, PartialEq)]
+pub enum Halt<A> {
+    Terminated(A),
+    Looped(A),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum VmError {
+    Overflow { pc: usize },
+    NegativePc { pc: usize, target: i64 },
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VmError::Overflow { pc } => write!(f, "accumulator overflow at {}", pc),
+            VmError::NegativePc { pc, target } => {
+                write!(f, "jump from {} to negative address {}", pc, target)
+            }
+        }
+    }
+}
+
+pub fn run<A>(instrs: &[Instruction]) -> Result<Halt<A>, VmError>
+where
+    A: CheckedAdd + Zero + From<i32>,
+{
+    Program::compile(instrs).run()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day_8::vm::*;
+    use crate::day_8::Instruction::*;
+    use num::BigInt;
+
+    #[test]
+    fn test() {
+        let instrs = vec![Acc(i32::MAX), Acc(1), Jmp(2), Acc(3)];
+        assert_eq!(Err(VmError::Overflow { pc: 1 }), run::<i32>(&instrs));
+        assert_eq!(Ok(Halt::Terminated(2147483648)), run::<i64>(&instrs));
+        assert_eq!(
+            Ok(Halt::Terminated(BigInt::from(2147483648i64))),
+            run::<BigInt>(&instrs)
+        );
+
+        let instrs = vec![Acc(i32::MIN), Acc(-1), Jmp(-2)];
+        assert_eq!(Err(VmError::Overflow { pc: 1 }), run::<i32>(&instrs));
+        assert_eq!(Ok(Halt::Looped(-2147483649)), run::<i64>(&instrs));
+
+        assert_eq!(Ok(Halt::Terminated(0)), run::<i32>(&[Jmp(5)]));
+
+        let instrs = vec![Nop(0), Acc(1), Jmp(-3)];
+        assert_eq!(
+            Err(VmError::NegativePc { pc: 2, target: -1 }),
+            run::<i32>(&instrs)
+        );
+        assert_eq!(
+            "jump from 2 to negative address -1",
+            run::<i64>(&instrs).unwrap_err().to_string()
+        );
+    }
+}
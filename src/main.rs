@@ -1,8 +1,33 @@
 mod grid;
 mod utils;
 
+mod day_13;
+mod day_14;
+mod day_15;
 mod day_18;
+mod day_8;
 
 fn main() {
-    day_18::eighteen().unwrap();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+
+    match args.as_slice() {
+        ["8"] => day_8::eight().unwrap(),
+        ["8", "bench"] => day_8::bench::bench().unwrap(),
+        ["8", "cfg"] => day_8::cfg::run().unwrap(),
+        ["8", "repair", options @ ..] => day_8::repair::run(options).unwrap(),
+        ["13"] => day_13::thirteen().unwrap(),
+        ["13", "departures", from, to] => day_13::departures(from, to).unwrap(),
+        ["13", path] => day_13::solve_file(path).unwrap(),
+        ["14"] => day_14::fourteen().unwrap(),
+        ["14", "bench"] => day_14::bench::bench().unwrap(),
+        ["14", command @ ("dump" | "diff"), options @ ..] => {
+            day_14::dump::run(command, options).unwrap()
+        }
+        ["15"] => day_15::fifteen().unwrap(),
+        ["15", "bench"] => day_15::bench::bench().unwrap(),
+        ["18", "bench"] => day_18::bench::bench().unwrap(),
+        ["calc"] => day_18::repl::calc().unwrap(),
+        _ => day_18::eighteen().unwrap(),
+    }
 }
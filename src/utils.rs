@@ -1,9 +1,12 @@
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+
 #[allow(dead_code)]
 pub fn as_groups(lines: Vec<String>) -> Vec<Vec<String>> {
     let mut grouped = Vec::new();
     let mut groups = Vec::new();
     for line in lines {
-        if line.is_empty() && grouped.len() > 0 {
+        if line.is_empty() && !grouped.is_empty() {
             groups.push(grouped);
             grouped = Vec::new();
         } else {
@@ -11,9 +14,20 @@ pub fn as_groups(lines: Vec<String>) -> Vec<Vec<String>> {
         }
     }
 
-    if grouped.len() > 0 {
+    if !grouped.is_empty() {
         groups.push(grouped);
     }
 
     groups
 }
+
+// Runs `f` `rounds` times, checking it gives the same answer every time, and
+// returns the answer with the average time per run.
+pub fn time<T: PartialEq + Debug>(rounds: u32, f: impl Fn() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let res = f();
+    for _ in 1..rounds {
+        assert_eq!(res, f());
+    }
+    (res, start.elapsed() / rounds)
+}